@@ -0,0 +1,402 @@
+use anyhow::{bail, Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Component, Path, PathBuf};
+
+/// Extract a .tar.gz or .zip SDK archive into `dest`.
+///
+/// Entries that would end up outside `dest` (absolute paths, `..`, symlinks
+/// pointing outside) abort the extraction with an error.
+pub fn extract(archive: &Path, dest: &Path, is_tarball: bool) -> Result<()> {
+    fs::create_dir_all(dest)
+        .with_context(|| format!("Failed to create {}", dest.display()))?;
+    let dest_canonical = dest
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", dest.display()))?;
+    
+    let count = if is_tarball {
+        extract_tar_gz(archive, &dest_canonical)
+    } else {
+        extract_zip(archive, &dest_canonical)
+    }
+    .with_context(|| format!("Failed to extract {}", archive.display()))?;
+    
+    println!("  {} entries extracted", count);
+    Ok(())
+}
+
+/// Archive type from a file name: Some(true) for .tar.gz/.tgz, Some(false) for .zip
+pub fn archive_kind(file_name: &str) -> Option<bool> {
+    let name = file_name.to_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(true)
+    } else if name.ends_with(".zip") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn extract_tar_gz(archive: &Path, dest: &Path) -> Result<usize> {
+    let file = File::open(archive)?;
+    let size = file.metadata()?.len();
+    
+    // Progress follows the compressed bytes read, the entry count is unknown upfront
+    let pb = ProgressBar::new(size);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {bytes}/{total_bytes} {wide_msg}")?
+            .progress_chars("#>-"),
+    );
+    
+    let gz = flate2::read::GzDecoder::new(BufReader::new(pb.wrap_read(file)));
+    let mut tar = tar::Archive::new(gz);
+    
+    let mut count = 0;
+    for entry in tar.entries().context("Corrupted tar archive")? {
+        let mut entry = entry.context("Corrupted tar entry")?;
+        let path = entry.path()?.into_owned();
+        let rel = safe_relative_path(&path)?;
+        if rel.as_os_str().is_empty() {
+            continue;
+        }
+        
+        match entry.header().entry_type() {
+            tar::EntryType::Symlink => {
+                let target = entry
+                    .link_name()?
+                    .with_context(|| format!("Symlink without target: {}", rel.display()))?
+                    .into_owned();
+                check_link_target(dest, &rel, &target)?;
+            }
+            tar::EntryType::Link => {
+                let target = entry
+                    .link_name()?
+                    .with_context(|| format!("Hard link without target: {}", rel.display()))?
+                    .into_owned();
+                safe_relative_path(&target)?;
+            }
+            tar::EntryType::Char | tar::EntryType::Block | tar::EntryType::Fifo => {
+                // Device nodes and pipes have no place in an SDK
+                continue;
+            }
+            _ => {}
+        }
+        
+        pb.set_message(rel.display().to_string());
+        let unpacked = entry
+            .unpack_in(dest)
+            .with_context(|| format!("Failed to extract '{}'", rel.display()))?;
+        if !unpacked {
+            bail!("Refusing to extract '{}': path escapes the destination", rel.display());
+        }
+        count += 1;
+    }
+    
+    pb.finish_and_clear();
+    Ok(count)
+}
+
+fn extract_zip(archive: &Path, dest: &Path) -> Result<usize> {
+    let file = File::open(archive)?;
+    let mut zip = zip::ZipArchive::new(BufReader::new(file)).context("Corrupted zip archive")?;
+    
+    let pb = ProgressBar::new(zip.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}")?
+            .progress_chars("#>-"),
+    );
+    
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i).context("Corrupted zip entry")?;
+        let rel = safe_relative_path(Path::new(entry.name()))?;
+        pb.inc(1);
+        if rel.as_os_str().is_empty() {
+            continue;
+        }
+        pb.set_message(rel.display().to_string());
+        let out_path = dest.join(&rel);
+        
+        if entry.is_dir() {
+            fs::create_dir_all(&out_path)?;
+            continue;
+        }
+        
+        if entry.is_symlink() {
+            let mut target = String::new();
+            io::Read::read_to_string(&mut entry, &mut target)?;
+            check_link_target(dest, &rel, Path::new(&target))?;
+            #[cfg(unix)]
+            {
+                if out_path.is_symlink() || out_path.exists() {
+                    fs::remove_file(&out_path)?;
+                }
+                std::os::unix::fs::symlink(&target, &out_path)
+                    .with_context(|| format!("Failed to create symlink '{}'", rel.display()))?;
+            }
+            continue;
+        }
+        
+        let parent = out_path.parent().unwrap_or(dest);
+        fs::create_dir_all(parent)?;
+        // An earlier symlink must not redirect writes outside the destination
+        if !parent.canonicalize()?.starts_with(dest) {
+            bail!("Refusing to extract '{}': path escapes the destination", rel.display());
+        }
+        
+        let mut out = File::create(&out_path)
+            .with_context(|| format!("Failed to create {}", out_path.display()))?;
+        io::copy(&mut entry, &mut out)
+            .with_context(|| format!("Failed to extract '{}'", rel.display()))?;
+        
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&out_path, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+    }
+    
+    pb.finish_and_clear();
+    Ok(zip.len())
+}
+
+/// Archive entry path as a plain relative path; rejects absolute paths and `..`
+pub fn safe_relative_path(path: &Path) -> Result<PathBuf> {
+    let mut rel = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => rel.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                bail!(
+                    "Refusing to extract '{}': path escapes the destination",
+                    path.display()
+                );
+            }
+        }
+    }
+    Ok(rel)
+}
+
+/// Ensure a symlink at `entry` (relative to `dest`) pointing to `target` stays inside `dest`.
+///
+/// The link's parent is resolved on disk, so links created earlier cannot be
+/// chained to climb out. `..` is only accepted as a leading component.
+pub fn check_link_target(dest: &Path, entry: &Path, target: &Path) -> Result<()> {
+    let escape = || {
+        anyhow::anyhow!(
+            "Refusing to extract symlink '{}' -> '{}': target escapes the destination",
+            entry.display(),
+            target.display()
+        )
+    };
+    
+    let parent = dest.join(entry.parent().unwrap_or(Path::new("")));
+    fs::create_dir_all(&parent)?;
+    let mut resolved = parent.canonicalize()?;
+    if !resolved.starts_with(dest) {
+        return Err(escape());
+    }
+    
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if !descended => {
+                resolved.pop();
+            }
+            Component::Normal(part) => {
+                descended = true;
+                resolved.push(part);
+            }
+            _ => return Err(escape()),
+        }
+    }
+    
+    if !resolved.starts_with(dest) {
+        return Err(escape());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    
+    /// Empty scratch directory, canonicalized like `extract` does with its destination
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mpf-dev-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+    
+    /// Tar header with a raw name, bypassing the checks `tar::Header::set_path` makes
+    fn raw_header(name: &str, link: Option<&str>, size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        let gnu = header.as_gnu_mut().unwrap();
+        gnu.name[..name.len()].copy_from_slice(name.as_bytes());
+        if let Some(link) = link {
+            gnu.linkname[..link.len()].copy_from_slice(link.as_bytes());
+        }
+        header.set_entry_type(if link.is_some() {
+            tar::EntryType::Symlink
+        } else {
+            tar::EntryType::Regular
+        });
+        header.set_size(size);
+        header.set_mode(0o644);
+        header.set_cksum();
+        header
+    }
+    
+    fn tar_gz(dir: &Path, entries: &[(&str, Option<&str>)]) -> PathBuf {
+        let path = dir.join("test.tar.gz");
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::fast(),
+        ));
+        for (name, link) in entries {
+            let data: &[u8] = if link.is_some() { b"" } else { b"data" };
+            builder
+                .append(&raw_header(name, *link, data.len() as u64), data)
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        path
+    }
+    
+    #[test]
+    fn relative_paths_are_kept() {
+        assert_eq!(
+            safe_relative_path(Path::new("bin/mpf-host")).unwrap(),
+            PathBuf::from("bin/mpf-host")
+        );
+        assert_eq!(
+            safe_relative_path(Path::new("./lib/./libmpf.so")).unwrap(),
+            PathBuf::from("lib/libmpf.so")
+        );
+    }
+    
+    #[test]
+    fn parent_and_absolute_paths_are_rejected() {
+        for path in ["../evil", "lib/../../evil", "lib/..", "/etc/passwd", "/"] {
+            assert!(safe_relative_path(Path::new(path)).is_err(), "{} accepted", path);
+        }
+    }
+    
+    #[cfg(windows)]
+    #[test]
+    fn drive_and_unc_prefixes_are_rejected() {
+        for path in [r"C:\Windows\evil.dll", r"C:evil", r"\\server\share\evil", r"\evil"] {
+            assert!(safe_relative_path(Path::new(path)).is_err(), "{} accepted", path);
+        }
+    }
+    
+    #[test]
+    fn symlinks_inside_the_destination_are_accepted() {
+        let dest = scratch("link-inside");
+        check_link_target(&dest, Path::new("lib/libmpf.so"), Path::new("libmpf.so.1")).unwrap();
+        check_link_target(&dest, Path::new("lib/host"), Path::new("../bin/mpf-host")).unwrap();
+        check_link_target(&dest, Path::new("a/b/c"), Path::new("./../../x")).unwrap();
+        let _ = fs::remove_dir_all(&dest);
+    }
+    
+    #[test]
+    fn symlinks_escaping_the_destination_are_rejected() {
+        let dest = scratch("link-escape");
+        let cases = [
+            ("lib/evil", "../../etc/passwd"),
+            ("evil", ".."),
+            ("evil", "/etc/passwd"),
+            // `..` after a normal component could climb through another symlink
+            ("lib/evil", "x/../../.."),
+        ];
+        for (entry, target) in cases {
+            assert!(
+                check_link_target(&dest, Path::new(entry), Path::new(target)).is_err(),
+                "{} -> {} accepted",
+                entry,
+                target
+            );
+        }
+        let _ = fs::remove_dir_all(&dest);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_cannot_be_chained_out_of_the_destination() {
+        let dest = scratch("link-chain");
+        std::os::unix::fs::symlink("..", dest.join("up")).unwrap();
+        assert!(check_link_target(&dest, Path::new("up/evil"), Path::new("x")).is_err());
+        let _ = fs::remove_dir_all(&dest);
+    }
+    
+    #[test]
+    fn tarball_with_escaping_entries_is_rejected() {
+        let dir = scratch("tar-escape");
+        for (i, entries) in [
+            vec![("bin/ok", None), ("../evil", None)],
+            vec![("/tmp/evil", None)],
+            vec![("evil", Some("/etc/passwd"))],
+            vec![("lib", Some("..")), ("lib/evil", None)],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let archive = tar_gz(&dir, &entries);
+            let dest = dir.join(format!("out{}", i));
+            assert!(extract(&archive, &dest, true).is_err(), "case {} extracted", i);
+            assert!(!dir.join("evil").exists());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn tarball_inside_the_destination_is_extracted() {
+        let dir = scratch("tar-ok");
+        let archive = tar_gz(&dir, &[("bin/mpf-host", None), ("lib/libmpf.so", Some("libmpf.so.1"))]);
+        let dest = dir.join("out");
+        extract(&archive, &dest, true).unwrap();
+        assert_eq!(fs::read(dest.join("bin/mpf-host")).unwrap(), b"data");
+        let _ = fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn zip_with_escaping_entries_is_rejected() {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+        
+        let dir = scratch("zip-escape");
+        for (i, (name, link)) in [("../evil", None), ("/tmp/evil", None), ("evil", Some("../.."))]
+            .into_iter()
+            .enumerate()
+        {
+            let archive = dir.join("test.zip");
+            let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+            match link {
+                Some(target) => zip.add_symlink(name, target, SimpleFileOptions::default()).unwrap(),
+                None => {
+                    zip.start_file(name, SimpleFileOptions::default()).unwrap();
+                    zip.write_all(b"data").unwrap();
+                }
+            }
+            zip.finish().unwrap();
+            let dest = dir.join(format!("out{}", i));
+            assert!(extract(&archive, &dest, false).is_err(), "{} extracted", name);
+            assert!(!dir.join("evil").exists());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+    
+    #[test]
+    fn archive_kinds() {
+        assert_eq!(archive_kind("mpf-linux-x64.tar.gz"), Some(true));
+        assert_eq!(archive_kind("SDK.TGZ"), Some(true));
+        assert_eq!(archive_kind("mpf-windows-x64.zip"), Some(false));
+        assert_eq!(archive_kind("mpf-linux-x64.tar.xz"), None);
+    }
+}
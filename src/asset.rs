@@ -0,0 +1,384 @@
+use anyhow::{bail, Result};
+use std::env::consts::{ARCH, OS};
+use std::path::Path;
+
+use crate::archive;
+use crate::release::ReleaseInfo;
+
+/// Architecture aliases used in asset names
+const ARCH_ALIASES: &[(&str, &[&str])] = &[
+    ("x86_64", &["x64", "x86_64", "amd64"]),
+    ("aarch64", &["arm64", "aarch64"]),
+];
+
+/// OS aliases used in asset names
+const OS_ALIASES: &[(&str, &[&str])] = &[
+    ("linux", &["linux"]),
+    ("windows", &["windows", "win", "win64"]),
+    ("macos", &["macos", "darwin", "osx", "mac"]),
+];
+
+/// Name parts of archives published alongside the SDK archive rather than instead of it
+const COMPANION_TOKENS: &[&str] = &["delta", "symbols", "sources"];
+
+/// Chooses the SDK archive for this machine from a release's asset list.
+///
+/// Asset names are split on `-` and `.` and matched on OS, architecture and,
+/// if requested, Qt flavour (e.g. `mpf-linux-arm64-gcc_64.tar.gz`).
+/// Names without any architecture are taken to be x86_64, like older releases.
+#[derive(Debug, Clone, Default)]
+pub struct AssetSelector {
+    /// Exact asset name (`--asset`), bypassing matching
+    pub name: Option<String>,
+    
+    /// Qt flavour that must appear in the name (`--qt`)
+    pub qt: Option<String>,
+    
+    /// Optional archives to install next to the SDK (`--with-symbols`, `--with-sources`)
+    pub companions: Vec<Companion>,
+}
+
+impl AssetSelector {
+    pub fn new(name: Option<String>, qt: Option<String>) -> Self {
+        Self {
+            name,
+            qt: qt.map(|q| q.to_lowercase()),
+            companions: Vec::new(),
+        }
+    }
+    
+    /// Asset to download from `release`, or the conventional name when the
+    /// release carries no asset list (static mirrors, missing metadata)
+    pub fn select(&self, release: Option<&ReleaseInfo>) -> Result<String> {
+        let release = release.filter(|r| !r.assets.is_empty());
+        
+        if let Some(name) = &self.name {
+            if let Some(r) = release {
+                if !r.has_asset(name) {
+                    bail!(
+                        "Release {} has no asset named '{}'.{}",
+                        r.tag,
+                        name,
+                        available_list(r)
+                    );
+                }
+            }
+            return Ok(name.clone());
+        }
+        
+        let Some(release) = release else {
+            if let Some(qt) = &self.qt {
+                bail!(
+                    "Cannot match Qt flavour '{}' without the release asset list; pass --asset <name>",
+                    qt
+                );
+            }
+            return Ok(conventional_name());
+        };
+        
+        let matching: Vec<&str> = release
+            .assets
+            .iter()
+            .map(|a| a.name.as_str())
+            .filter(|name| self.matches(name))
+            .collect();
+        
+        // Several matches: prefer this platform's usual archive type
+        let preferred: Vec<&str> = if matching.len() > 1 {
+            matching
+                .iter()
+                .copied()
+                .filter(|name| archive::archive_kind(name) == Some(!cfg!(target_os = "windows")))
+                .collect()
+        } else {
+            matching.clone()
+        };
+        
+        match preferred.as_slice() {
+            [name] => Ok(name.to_string()),
+            [] if matching.is_empty() => bail!(
+                "Release {} has no SDK archive for {}.{}\nPass --asset <name> to pick one.",
+                release.tag,
+                self.describe(),
+                available_list(release)
+            ),
+            _ => {
+                let names = if preferred.is_empty() { &matching } else { &preferred };
+                bail!(
+                    "Several assets in release {} match {}:\n{}\nPass --qt <flavour> or --asset <name> to pick one.",
+                    release.tag,
+                    self.describe(),
+                    names
+                        .iter()
+                        .map(|n| format!("  - {}", n))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )
+            }
+        }
+    }
+    
+    /// Whether an asset name is an SDK archive for this OS, architecture and Qt flavour
+    pub fn matches(&self, name: &str) -> bool {
+        self.matches_target(name, OS, ARCH)
+    }
+    
+    /// Whether an asset name is an SDK archive for `os`, `arch` and the Qt flavour
+    fn matches_target(&self, name: &str, os: &str, arch: &str) -> bool {
+        if archive::archive_kind(name).is_none() {
+            return false;
+        }
+        let tokens = name_tokens(name);
+        let has_any = |aliases: &[&str]| tokens.iter().any(|t| aliases.contains(&t.as_str()));
+        if has_any(COMPANION_TOKENS) {
+            return false;
+        }
+        
+        let os_ok = has_any(aliases_for(OS_ALIASES, os));
+        let arch_ok = if ARCH_ALIASES.iter().any(|(_, aliases)| has_any(aliases)) {
+            has_any(aliases_for(ARCH_ALIASES, arch))
+        } else {
+            arch == "x86_64"
+        };
+        let qt_ok = self.qt.as_ref().is_none_or(|qt| tokens.contains(qt));
+        
+        os_ok && arch_ok && qt_ok
+    }
+    
+    /// Human-readable target, e.g. "linux-aarch64 (Qt gcc_64)"
+    pub fn describe(&self) -> String {
+        match &self.qt {
+            Some(qt) => format!("{}-{} (Qt {})", OS, ARCH, qt),
+            None => format!("{}-{}", OS, ARCH),
+        }
+    }
+}
+
+/// Optional archive published next to an SDK archive for debugging it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Companion {
+    /// Separate debug info, laid out by build ID (`.build-id/ab/cdef....debug`)
+    Symbols,
+    
+    /// Source tree the SDK was built from
+    Sources,
+}
+
+impl Companion {
+    pub const ALL: [Companion; 2] = [Companion::Symbols, Companion::Sources];
+    
+    /// Asset name for the SDK archive `asset`, e.g. `mpf-linux-x64-symbols.tar.gz`
+    pub fn asset_name(self, asset: &str) -> String {
+        companion_name(asset, self.token())
+    }
+    
+    /// Directory inside the version directory; the `.mpf-` prefix keeps it out of the manifest
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            Companion::Symbols => ".mpf-symbols",
+            Companion::Sources => ".mpf-sources",
+        }
+    }
+    
+    /// Companions installed in a version directory
+    pub fn installed(version_dir: &Path) -> Vec<Companion> {
+        Self::ALL
+            .into_iter()
+            .filter(|c| version_dir.join(c.dir_name()).is_dir())
+            .collect()
+    }
+    
+    /// Name part identifying the companion, also used in `versions --json`
+    pub fn token(self) -> &'static str {
+        match self {
+            Companion::Symbols => "symbols",
+            Companion::Sources => "sources",
+        }
+    }
+}
+
+impl std::fmt::Display for Companion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Companion::Symbols => write!(f, "debug symbols"),
+            Companion::Sources => write!(f, "sources"),
+        }
+    }
+}
+
+/// Name of an archive published alongside `asset`: `<stem>-<suffix>.<ext>`
+pub fn companion_name(asset: &str, suffix: &str) -> String {
+    for ext in [".tar.gz", ".tgz", ".zip"] {
+        if let Some(stem) = asset.strip_suffix(ext) {
+            return format!("{}-{}{}", stem, suffix, ext);
+        }
+    }
+    format!("{}-{}", asset, suffix)
+}
+
+/// Asset name used when the release publishes no asset list
+fn conventional_name() -> String {
+    let arch = match ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        other => other,
+    };
+    let ext = if cfg!(target_os = "windows") { "zip" } else { "tar.gz" };
+    format!("mpf-{}-{}.{}", OS, arch, ext)
+}
+
+fn aliases_for(table: &'static [(&str, &[&'static str])], key: &str) -> &'static [&'static str] {
+    table
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, aliases)| *aliases)
+        .unwrap_or(&[])
+}
+
+/// Lowercase name parts, without the archive extension
+fn name_tokens(name: &str) -> Vec<String> {
+    let lower = name.to_lowercase();
+    let stem = lower
+        .trim_end_matches(".tar.gz")
+        .trim_end_matches(".tgz")
+        .trim_end_matches(".zip");
+    stem.split(['-', '.'])
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+fn available_list(release: &ReleaseInfo) -> String {
+    let mut list = String::from("\nAvailable assets:");
+    for asset in &release.assets {
+        list.push_str(&format!("\n  - {}", asset.name));
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn release(assets: &[&str]) -> ReleaseInfo {
+        let assets: Vec<_> = assets
+            .iter()
+            .map(|name| serde_json::json!({ "name": name }))
+            .collect();
+        serde_json::from_value(serde_json::json!({ "tag": "v1.2.3", "assets": assets })).unwrap()
+    }
+    
+    /// Asset name for this machine with `extra` name parts, in its usual archive type
+    fn native(extra: &str) -> String {
+        let stem = conventional_name();
+        let ext = if cfg!(target_os = "windows") { ".zip" } else { ".tar.gz" };
+        format!("{}{}{}", stem.strip_suffix(ext).unwrap(), extra, ext)
+    }
+    
+    #[test]
+    fn names_match_os_arch_and_qt_flavour() {
+        let any_qt = AssetSelector::new(None, None);
+        let gcc = AssetSelector::new(None, Some("GCC_64".into()));
+        // (name, os, arch, matches without --qt, matches --qt gcc_64)
+        let table = [
+            ("mpf-linux-x64.tar.gz", "linux", "x86_64", true, false),
+            ("mpf-linux-amd64-gcc_64.tar.gz", "linux", "x86_64", true, true),
+            ("MPF-Linux-X86_64-gcc_64.tgz", "linux", "x86_64", true, true),
+            ("mpf-linux-arm64-gcc_64.tar.gz", "linux", "aarch64", true, true),
+            ("mpf-linux-aarch64.tar.gz", "linux", "aarch64", true, false),
+            ("mpf-linux-arm64.tar.gz", "linux", "x86_64", false, false),
+            ("mpf-linux-x64.tar.gz", "linux", "aarch64", false, false),
+            // No architecture: older x86_64-only releases
+            ("mpf-linux.tar.gz", "linux", "x86_64", true, false),
+            ("mpf-linux.tar.gz", "linux", "aarch64", false, false),
+            ("mpf-win64-msvc2019_64.zip", "windows", "x86_64", true, false),
+            ("mpf-win-arm64.zip", "windows", "aarch64", true, false),
+            ("mpf-windows-x64.zip", "linux", "x86_64", false, false),
+            ("mpf-darwin-arm64.tar.gz", "macos", "aarch64", true, false),
+            ("mpf-osx.zip", "macos", "x86_64", true, false),
+            ("mpf-macos-x64.tar.gz", "linux", "x86_64", false, false),
+            // Name parts, not substrings
+            ("mpf-winter-x64.zip", "windows", "x86_64", false, false),
+            ("mpf-linux-x64-gcc_64_static.tar.gz", "linux", "x86_64", true, false),
+            // Not an archive
+            ("mpf-linux-x64.tar.gz.sha256", "linux", "x86_64", false, false),
+            ("mpf-linux-x64.deb", "linux", "x86_64", false, false),
+        ];
+        for (name, os, arch, plain, qt) in table {
+            let target = format!("{} on {}-{}", name, os, arch);
+            assert_eq!(any_qt.matches_target(name, os, arch), plain, "{}", target);
+            assert_eq!(gcc.matches_target(name, os, arch), qt, "{} (Qt gcc_64)", target);
+        }
+    }
+    
+    #[test]
+    fn companion_archives_are_never_selected() {
+        let selector = AssetSelector::new(None, None);
+        for name in [
+            "mpf-linux-x64-symbols.tar.gz",
+            "mpf-linux-x64-sources.tar.gz",
+            "mpf-linux-x64-delta.tar.gz",
+            "mpf-linux-x64-gcc_64-Symbols.tar.gz",
+        ] {
+            assert!(!selector.matches_target(name, "linux", "x86_64"), "{}", name);
+        }
+        
+        let sdk = native("");
+        let assets = [
+            Companion::Symbols.asset_name(&sdk),
+            sdk.clone(),
+            Companion::Sources.asset_name(&sdk),
+        ];
+        let assets: Vec<&str> = assets.iter().map(String::as_str).collect();
+        assert_eq!(selector.select(Some(&release(&assets))).unwrap(), sdk);
+    }
+    
+    #[test]
+    fn select_prefers_the_usual_archive_type() {
+        let native = native("");
+        let other = if native.ends_with(".zip") {
+            native.replace(".zip", ".tar.gz")
+        } else {
+            native.replace(".tar.gz", ".zip")
+        };
+        let selector = AssetSelector::new(None, None);
+        assert_eq!(selector.select(Some(&release(&[&other, &native]))).unwrap(), native);
+        assert_eq!(selector.select(Some(&release(&[&other]))).unwrap(), other);
+    }
+    
+    #[test]
+    fn select_reports_ambiguous_and_missing_assets() {
+        let gcc = native("-gcc_64");
+        let clang = native("-clang_64");
+        let assets = release(&[&gcc, &clang, "mpf-plan9-x64.tar.gz"]);
+        
+        let err = AssetSelector::new(None, None).select(Some(&assets)).unwrap_err().to_string();
+        assert!(err.contains("Several assets"), "{}", err);
+        assert!(err.contains(&gcc) && err.contains(&clang), "{}", err);
+        assert!(!err.contains("plan9"), "{}", err);
+        
+        let clang_only = AssetSelector::new(None, Some("clang_64".into()));
+        assert_eq!(clang_only.select(Some(&assets)).unwrap(), clang);
+        
+        let err = AssetSelector::new(None, Some("mingw".into()))
+            .select(Some(&assets))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no SDK archive"), "{}", err);
+        assert!(err.contains("mpf-plan9-x64.tar.gz"), "{}", err);
+        
+        let err = AssetSelector::new(Some("mpf-missing.zip".into()), None)
+            .select(Some(&assets))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("no asset named 'mpf-missing.zip'"), "{}", err);
+    }
+    
+    #[test]
+    fn select_without_asset_list() {
+        assert_eq!(AssetSelector::new(None, None).select(None).unwrap(), conventional_name());
+        let named = AssetSelector::new(Some("custom.tar.gz".into()), None);
+        assert_eq!(named.select(Some(&release(&[]))).unwrap(), "custom.tar.gz");
+        assert!(AssetSelector::new(None, Some("gcc_64".into())).select(None).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::archive;
+use crate::asset::AssetSelector;
+use crate::config;
+use crate::manifest;
+use crate::signature;
+use crate::version;
+
+/// A downloaded SDK archive kept in ~/.mpf-sdk/cache/<version>/<asset>
+#[derive(Debug)]
+pub struct CachedArchive {
+    pub version: String,
+    pub asset: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: SystemTime,
+}
+
+/// Path of the archive for `asset` of `version`
+pub fn archive_path(version: &str, asset: &str) -> Result<PathBuf> {
+    version::validate(version)?;
+    Ok(config::cache_dir()?.join(version).join(asset))
+}
+
+/// Checksum file stored next to a cached archive ("<hash>  <asset>")
+fn checksum_path(archive: &Path) -> PathBuf {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    archive.with_file_name(format!("{}.sha256", name))
+}
+
+/// Detached signature stored next to a cached archive
+fn signature_path(archive: &Path) -> PathBuf {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    archive.with_file_name(signature::signature_name(&name))
+}
+
+/// Checksum recorded when the archive was cached
+pub fn checksum(archive: &Path) -> Option<String> {
+    fs::read_to_string(checksum_path(archive))
+        .ok()
+        .and_then(|s| s.split_whitespace().next().map(|h| h.to_string()))
+}
+
+/// Signature the cached archive was verified with, if it had one
+pub fn signature(archive: &Path) -> Option<String> {
+    fs::read_to_string(signature_path(archive)).ok()
+}
+
+/// Cached archive of `version` that `selector` accepts, after checking it
+/// against its stored checksum. Corrupted entries are deleted.
+pub fn find(version: &str, selector: &AssetSelector) -> Option<CachedArchive> {
+    let mut candidates: Vec<CachedArchive> = entries()
+        .into_iter()
+        .filter(|e| e.version == version)
+        .filter(|e| match &selector.name {
+            Some(name) => e.asset == *name,
+            None => selector.matches(&e.asset),
+        })
+        .collect();
+    if candidates.len() != 1 {
+        return None;
+    }
+    let entry = candidates.pop()?;
+    
+    let expected = checksum(&entry.path);
+    let actual = manifest::sha256_file(&entry.path).ok();
+    match (expected, actual) {
+        (Some(expected), Some(actual)) if expected.eq_ignore_ascii_case(&actual) => Some(entry),
+        _ => {
+            println!(
+                "{} Cached {} for {} is corrupted; downloading again",
+                "Warning:".yellow(),
+                entry.asset,
+                version
+            );
+            remove(&entry);
+            None
+        }
+    }
+}
+
+/// Move a downloaded archive into the cache and record its checksum and signature.
+/// `sha256` must be the published checksum the download was verified against:
+/// cached archives are later trusted on that record alone.
+pub fn store(
+    download: &Path,
+    version: &str,
+    asset: &str,
+    sha256: &str,
+    signature: Option<&str>,
+) -> Result<PathBuf> {
+    let sha256 = sha256.to_lowercase();
+    let path = archive_path(version, asset)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(download, &path)
+        .with_context(|| format!("Failed to move {} into the cache", download.display()))?;
+    fs::write(checksum_path(&path), format!("{}  {}\n", sha256, asset))?;
+    match signature {
+        Some(signature) => fs::write(signature_path(&path), signature)?,
+        None => {
+            let _ = fs::remove_file(signature_path(&path));
+        }
+    }
+    Ok(path)
+}
+
+/// Mark a cached archive as used now (`prune` drops the least recently used first)
+pub fn touch(path: &Path) {
+    if let Ok(file) = File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// Every cached archive
+pub fn entries() -> Vec<CachedArchive> {
+    let mut found = Vec::new();
+    let Some(versions) = config::cache_dir().ok().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return found;
+    };
+    for version_entry in versions.filter_map(|e| e.ok()) {
+        let version = version_entry.file_name().to_string_lossy().to_string();
+        let Ok(files) = fs::read_dir(version_entry.path()) else {
+            continue;
+        };
+        for file in files.filter_map(|e| e.ok()) {
+            let asset = file.file_name().to_string_lossy().to_string();
+            let Ok(meta) = file.metadata() else {
+                continue;
+            };
+            if !meta.is_file() || archive::archive_kind(&asset).is_none() {
+                continue;
+            }
+            found.push(CachedArchive {
+                version: version.clone(),
+                asset,
+                path: file.path(),
+                size: meta.len(),
+                last_used: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+    }
+    found
+}
+
+/// Delete a cached archive, its checksum and signature and, if now empty, its version directory
+pub fn remove(entry: &CachedArchive) {
+    let _ = fs::remove_file(&entry.path);
+    let _ = fs::remove_file(checksum_path(&entry.path));
+    let _ = fs::remove_file(signature_path(&entry.path));
+    if let Some(dir) = entry.path.parent() {
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+/// Parse a size such as "500M", "2G", "1.5GB" or "1048576" into bytes
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let upper = text.to_uppercase();
+    let number_part = upper.strip_suffix('B').unwrap_or(&upper);
+    let number_part = number_part.strip_suffix('I').unwrap_or(number_part);
+    let (number, unit) = match number_part.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&number_part[..i], c),
+        _ => (number_part, ' '),
+    };
+    let multiplier: u64 = match unit {
+        ' ' => 1,
+        'K' => 1 << 10,
+        'M' => 1 << 20,
+        'G' => 1 << 30,
+        'T' => 1 << 40,
+        _ => bail!("Invalid size '{}' (use e.g. 500M or 2G)", text),
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .ok()
+        .filter(|v: &f64| *v >= 0.0)
+        .with_context(|| format!("Invalid size '{}' (use e.g. 500M or 2G)", text))?;
+    Ok((value * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn sizes_with_units() {
+        assert_eq!(parse_size("1048576").unwrap(), 1 << 20);
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("512K").unwrap(), 512 << 10);
+        assert_eq!(parse_size("500M").unwrap(), 500 << 20);
+        assert_eq!(parse_size("500mb").unwrap(), 500 << 20);
+        assert_eq!(parse_size("2G").unwrap(), 2 << 30);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 << 30);
+        assert_eq!(parse_size("1.5GB").unwrap(), 3 << 29);
+        assert_eq!(parse_size("1T").unwrap(), 1 << 40);
+        assert_eq!(parse_size(" 2 G ").unwrap(), 2 << 30);
+        assert_eq!(parse_size("100B").unwrap(), 100);
+    }
+    
+    #[test]
+    fn invalid_sizes() {
+        for text in ["", "B", "G", "abc", "-1G", "1.5.2M", "2X", "1P", "1BB", "1GG", "1 GiBB", "NaN"] {
+            assert!(parse_size(text).is_err(), "{:?} accepted", text);
+        }
+    }
+}
@@ -5,15 +5,28 @@ use indicatif::{ProgressBar, ProgressStyle};
 use std::env;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::archive;
+use crate::asset::{AssetSelector, Companion};
+use crate::cache;
 use crate::config::{
     self, ComponentConfig, ComponentMode, DevConfig, KNOWN_COMPONENTS,
 };
+use crate::dedupe;
+use crate::delta;
+use crate::http;
+use crate::install_info::{InstallInfo, InstallMethod};
+use crate::manifest::{self, Manifest};
+use crate::release::{self, ReleaseSource};
+use crate::self_update;
+use crate::signature::{self, SignaturePolicy};
+use crate::version::{self, Channel, VersionSpec};
 use crate::LinkAction;
 
-const GITHUB_REPO: &str = "dyzdyz010/mpf-release";
+/// Checksum list published alongside the SDK archives in each release
+const CHECKSUMS_FILE: &str = "SHA256SUMS";
 
 /// Normalize a path by removing .\ and .. components
 fn normalize_path(p: PathBuf) -> String {
@@ -27,35 +40,84 @@ fn normalize_path(p: PathBuf) -> String {
     };
     
     // Remove Windows extended path prefix (\\?\)
-    if result.starts_with(r"\\?\") {
-        result[4..].to_string()
-    } else {
-        result
+    match result.strip_prefix(r"\\?\") {
+        Some(stripped) => stripped.to_string(),
+        None => result,
     }
 }
 
 /// Setup command: download and install SDK
-pub async fn setup(version: Option<String>) -> Result<()> {
+pub async fn setup(
+    source: &ReleaseSource,
+    version: Option<String>,
+    channel: Channel,
+    from_file: Option<String>,
+    selector: AssetSelector,
+    skip_verify: bool,
+    locked: bool,
+) -> Result<()> {
     println!("{}", "MPF SDK Setup".bold().cyan());
     
-    let version = match version {
-        Some(v) => v,
-        None => {
-            println!("Fetching latest release...");
-            fetch_latest_version().await?
+    let archive = from_file.map(PathBuf::from);
+    
+    // --locked installs the version named by the project's pin file
+    let pin_file = if locked {
+        let pin = config::pin_files_from_cwd().into_iter().next().with_context(|| {
+            format!(
+                "No {} found in this directory or its parents. Pin a version with `mpf-dev use --local <version>`",
+                config::PIN_FILE
+            )
+        })?;
+        println!("Using version pinned in {}", pin.display());
+        Some(pin)
+    } else {
+        None
+    };
+    let version = match &pin_file {
+        Some(pin) => Some(
+            config::read_pin_file(pin)?.with_context(|| format!("{} is empty", pin.display()))?,
+        ),
+        None => version,
+    };
+    
+    let version = match (version, &archive) {
+        (Some(label), Some(_)) => {
+            // The label becomes the version directory's name
+            version::validate(&version::normalize(&label))?;
+            if version::parse_version(&label).is_none() {
+                bail!("Invalid version label '{}' for --from-file (expected e.g. 1.2.3)", label);
+            }
+            label
+        }
+        (None, Some(archive)) => version_from_file_name(archive).with_context(|| {
+            format!(
+                "Could not infer the SDK version from '{}'. Pass --version to label it.",
+                archive.display()
+            )
+        })?,
+        (spec, None) => {
+            resolve_release_version(source, spec.as_deref(), channel, &selector).await?
         }
     };
     
-    let version_normalized = if version.starts_with('v') {
-        version.clone()
+    let version_normalized = version::normalize(&version);
+    
+    println!("Installing SDK version: {}", version_normalized.green());
+    
+    // An installed copy in either store is reused; with --system only the system store counts
+    let version_dir = if config::system_store_selected() {
+        config::install_dir(&version_normalized)?
     } else {
-        format!("v{}", version)
+        config::version_dir(&version_normalized)?
     };
+    let sdk_root = version_dir.parent().map(Path::to_path_buf).unwrap_or_default();
     
-    println!("Installing SDK version: {}", version_normalized.green());
+    if config::system_store_selected() && !version_dir.exists() {
+        ensure_writable(&sdk_root)?;
+    }
     
-    let sdk_root = config::sdk_root();
-    let version_dir = config::version_dir(&version_normalized);
+    // Leftovers from interrupted runs
+    cleanup_stale_install_files();
     
     // Check if already installed
     if version_dir.exists() {
@@ -65,189 +127,2055 @@ pub async fn setup(version: Option<String>) -> Result<()> {
             version_normalized
         );
     } else {
-        // Download and extract
-        download_and_extract(&version_normalized, &version_dir).await?;
+        if let Some(archive) = &archive {
+            // Install from an archive already on disk
+            install_from_file(archive, &version_dir, skip_verify)?;
+        } else {
+            // Download and extract
+            download_and_extract(source, &version_normalized, &version_dir, &selector, skip_verify)
+                .await?;
+        }
+        dedupe_after_install(&sdk_root);
+    }
+    
+    // Debugging aids can also be added to a version installed earlier
+    install_companions(source, &version_normalized, &version_dir, &selector, skip_verify).await?;
+    
+    if config::system_store_selected() {
+        // Each user picks system versions themselves
+        println!(
+            "{} SDK {} installed in the system store",
+            "✓".green(),
+            version_normalized
+        );
+        println!("  Location: {}", sdk_root.display());
+        println!("  Users can switch to it with `mpf-dev use {}`", version_normalized);
+        return Ok(());
+    }
+    
+    if let Some(pin) = &pin_file {
+        // A pinned install leaves the global version alone unless there is none yet
+        register_pin_file(pin)?;
+        if config::current_version().is_none() {
+            config::set_current_version(&version_normalized)?;
+        }
+        println!(
+            "{} SDK {} installed for {}",
+            "✓".green(),
+            version_normalized,
+            pin.display()
+        );
+        println!("  Location: {}", sdk_root.display());
+        return Ok(());
+    }
+    
+    // Set as current
+    config::set_current_version(&version_normalized)?;
+    
+    // Update dev.json
+    let mut config = DevConfig::load().unwrap_or_default();
+    config.sdk_version = Some(version_normalized.clone());
+    config.save()?;
+    
+    println!(
+        "{} SDK {} installed and set as current",
+        "✓".green(),
+        version_normalized
+    );
+    println!("  Location: {}", sdk_root.display());
+    
+    Ok(())
+}
+
+/// Download the companion archives requested by `selector` (debug symbols,
+/// sources) into an installed version. The SDK itself works without them, so
+/// a companion that is missing or fails to verify is skipped with a warning.
+async fn install_companions(
+    source: &ReleaseSource,
+    version: &str,
+    version_dir: &Path,
+    selector: &AssetSelector,
+    skip_verify: bool,
+) -> Result<()> {
+    let wanted: Vec<Companion> = selector
+        .companions
+        .iter()
+        .copied()
+        .filter(|c| !version_dir.join(c.dir_name()).is_dir())
+        .collect();
+    if wanted.is_empty() {
+        return Ok(());
+    }
+    
+    // A version found only in the system store is read-only without --system
+    if *version_dir != config::install_dir(version)? {
+        println!(
+            "{} SDK {} is installed in the system store; an administrator can add {} with `mpf-dev setup --system --version {}{}`",
+            "Warning:".yellow(),
+            version,
+            wanted.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" and "),
+            version,
+            wanted.iter().map(|c| format!(" --with-{}", c.token())).collect::<String>()
+        );
+        return Ok(());
+    }
+    
+    let policy = SignaturePolicy::load()?;
+    if skip_verify {
+        policy.check_skip()?;
+    }
+    let client = http::client()?;
+    let release_info = release::fetch_release(client, source, version).await?;
+    
+    // Companions are named after the archive the version was installed from
+    let asset_name = match Manifest::load(version_dir).ok().flatten().and_then(|m| m.asset) {
+        Some(asset) => asset,
+        None => selector.select(release_info.as_ref())?,
+    };
+    
+    for companion in wanted {
+        let name = companion.asset_name(&asset_name);
+        if release_info
+            .as_ref()
+            .is_some_and(|r| !r.assets.is_empty() && !r.has_asset(&name))
+        {
+            println!(
+                "{} Release {} publishes no {} ({})",
+                "Warning:".yellow(),
+                version,
+                companion,
+                name
+            );
+            continue;
+        }
+        
+        let result = install_companion(
+            client,
+            source,
+            release_info.as_ref(),
+            version,
+            &name,
+            &version_dir.join(companion.dir_name()),
+            (!skip_verify).then_some(&policy),
+        )
+        .await;
+        match result {
+            Ok(()) => println!("{} Installed {} ({})", "✓".green(), companion, name),
+            Err(e) => println!(
+                "{} Could not install {}: {:#}",
+                "Warning:".yellow(),
+                companion,
+                e
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Download, verify and extract one companion archive into `dest`
+async fn install_companion(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    release_info: Option<&release::ReleaseInfo>,
+    version: &str,
+    name: &str,
+    dest: &Path,
+    verify: Option<&SignaturePolicy>,
+) -> Result<()> {
+    let is_tarball = archive::archive_kind(name).unwrap_or(true);
+    let dir_name = dest.file_name().unwrap_or_default().to_string_lossy();
+    let temp_ext = if is_tarball { "tar.gz.tmp" } else { "zip.tmp" };
+    let temp_path = dest.with_file_name(format!("{}.{}", dir_name, temp_ext));
+    let staging = dest.with_file_name(format!("{}.staging", dir_name));
+    
+    println!("Downloading {} ({})...", name, version);
+    let result = async {
+        let url = source.asset_download_url(release_info, version, name);
+        download_with_retry(client, source, &url, &temp_path).await?;
+        if let Some(policy) = verify {
+            verify_download(client, source, release_info, version, name, &temp_path, policy).await?;
+        }
+        
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        archive::extract(&temp_path, &staging, is_tarball)?;
+        fs::rename(&staging, dest)
+            .with_context(|| format!("Failed to move {} into {}", name, dest.display()))
+    }
+    .await;
+    
+    // Companions are small next to the SDK; a failed download is simply started over
+    let _ = fs::remove_dir_all(&staging);
+    let _ = fs::remove_file(&temp_path);
+    result
+}
+
+/// Share a new install's files with the other versions in `root`.
+/// Only an optimisation, so failures are warnings.
+fn dedupe_after_install(root: &Path) {
+    match dedupe::dedupe_store(root) {
+        Ok(report) if report.linked > 0 => println!(
+            "{} Shared {} identical file(s) with other versions ({} saved)",
+            "✓".green(),
+            report.linked,
+            indicatif::HumanBytes(report.saved)
+        ),
+        Ok(_) => {}
+        Err(e) => println!("{} Could not deduplicate SDK files: {:#}", "Warning:".yellow(), e),
+    }
+}
+
+/// Fail early, before downloading, when the system store cannot be written
+fn ensure_writable(dir: &Path) -> Result<()> {
+    let hint = || {
+        format!(
+            "Cannot write to the system SDK store {} (run as an administrator, or set MPF_SDK_SYSTEM_HOME)",
+            dir.display()
+        )
+    };
+    fs::create_dir_all(dir).with_context(hint)?;
+    let probe = dir.join(format!(".mpf-write-test-{}", std::process::id()));
+    File::create(&probe).with_context(hint)?;
+    let _ = fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Pick a version label such as "v1.2.3" or "1.2.3" out of an archive file name
+/// (e.g. "mpf-linux-x64-v1.2.3.tar.gz")
+fn version_from_file_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let stem = name
+        .trim_end_matches(".tar.gz")
+        .trim_end_matches(".tgz")
+        .trim_end_matches(".zip");
+    
+    stem.split(['-', '_'])
+        .find(|part| {
+            let digits = part.strip_prefix('v').unwrap_or(part);
+            digits.contains('.')
+                && digits.split('.').all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|s| s.to_string())
+}
+
+/// Install an SDK archive from the local filesystem
+fn install_from_file(archive: &Path, dest: &Path, skip_verify: bool) -> Result<()> {
+    if !archive.is_file() {
+        bail!("Archive not found: {}", archive.display());
+    }
+    let file_name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let is_tarball = archive::archive_kind(&file_name).with_context(|| {
+        format!(
+            "Unsupported archive type: {} (expected .tar.gz or .zip)",
+            archive.display()
+        )
+    })?;
+    
+    println!("Using local archive {}", archive.display());
+    
+    let policy = SignaturePolicy::load()?;
+    let mut expected_checksum = None;
+    if skip_verify {
+        policy.check_skip()?;
+        println!(
+            "{} Skipping checksum and signature verification (--skip-verify)",
+            "Warning:".yellow()
+        );
+    } else {
+        if let Some(expected) = local_expected_checksum(archive)? {
+            verify_checksum(archive, &file_name, &expected)?;
+            expected_checksum = Some(expected);
+        } else {
+            println!(
+                "{} No {} or .sha256 file next to the archive; skipping checksum verification",
+                "Note:".yellow(),
+                CHECKSUMS_FILE
+            );
+        }
+        
+        // A sibling `<archive>.minisig` is checked like a published one
+        let signature_path = archive.with_file_name(signature::signature_name(&file_name));
+        let signature = if signature_path.is_file() {
+            Some(
+                fs::read_to_string(&signature_path)
+                    .with_context(|| format!("Failed to read {}", signature_path.display()))?,
+            )
+        } else {
+            None
+        };
+        policy.verify(archive, &file_name, signature.as_deref())?;
+    }
+    
+    println!("Extracting...");
+    let sha256 = match expected_checksum {
+        Some(hash) => Some(hash),
+        None => Some(manifest::sha256_file(archive)?),
+    };
+    let source = std::path::absolute(archive).unwrap_or_else(|_| archive.to_path_buf());
+    let info = InstallInfo::new(
+        InstallMethod::File,
+        Some(source.display().to_string()),
+        None,
+        sha256,
+    );
+    extract_atomically(archive, dest, is_tarball, info)?;
+    
+    println!("{} Extraction complete", "✓".green());
+    Ok(())
+}
+
+/// Checksum for a local archive from a sibling `<archive>.sha256` or SHA256SUMS file
+fn local_expected_checksum(archive: &Path) -> Result<Option<String>> {
+    let name = archive.file_name().unwrap_or_default().to_string_lossy().to_string();
+    
+    let asset_sum = archive.with_file_name(format!("{}.sha256", name));
+    if asset_sum.is_file() {
+        let content = fs::read_to_string(&asset_sum)?;
+        return Ok(content
+            .split_whitespace()
+            .next()
+            .filter(|h| is_sha256(h))
+            .map(|s| s.to_string()));
+    }
+    
+    let sums = archive.with_file_name(CHECKSUMS_FILE);
+    if sums.is_file() {
+        let content = fs::read_to_string(&sums)?;
+        return Ok(parse_checksum_list(&content, &name));
+    }
+    
+    Ok(None)
+}
+
+/// Turn a `--version` spec (exact tag, range, `latest`, `latest-patch`) and a
+/// channel into a concrete release tag
+async fn resolve_release_version(
+    source: &ReleaseSource,
+    spec_text: Option<&str>,
+    channel: Channel,
+    selector: &AssetSelector,
+) -> Result<String> {
+    let spec_text = spec_text.unwrap_or("latest");
+    let spec = VersionSpec::parse(spec_text)?;
+    
+    match &spec {
+        VersionSpec::Exact(tag) => return Ok(tag.clone()),
+        VersionSpec::Latest if channel == Channel::Stable => {
+            println!("Fetching latest release...");
+            return fetch_latest_version(source).await;
+        }
+        _ => {}
+    }
+    
+    println!(
+        "Resolving '{}' ({} channel) against available releases...",
+        spec_text,
+        channel.name()
+    );
+    let index = load_release_index(source, false, true).await?;
+    let candidates = installable_tags(&index, selector);
+    
+    let current = config::current_version();
+    spec.resolve(candidates, channel, current.as_deref())?
+        .with_context(|| {
+            format!(
+                "No {} release matches '{}'",
+                channel.name(),
+                spec_text
+            )
+        })
+}
+
+/// Tags of releases that have an asset for this platform (or publish no asset list)
+fn installable_tags<'a>(index: &'a release::ReleaseIndex, selector: &AssetSelector) -> Vec<&'a String> {
+    index
+        .releases
+        .iter()
+        .filter(|r| r.assets.is_empty() || selector.select(Some(r)).is_ok())
+        .map(|r| &r.tag)
+        .collect()
+}
+
+async fn fetch_latest_version(source: &ReleaseSource) -> Result<String> {
+    let client = http::client()?;
+    let resp = release::fetch_json(client, source, &source.latest_release_url()).await?;
+    
+    resp["tag_name"]
+        .as_str()
+        .map(|s| s.to_string())
+        .context("Could not find latest release")
+}
+
+async fn download_and_extract(
+    source: &ReleaseSource,
+    version: &str,
+    dest: &Path,
+    selector: &AssetSelector,
+    skip_verify: bool,
+) -> Result<()> {
+    let policy = SignaturePolicy::load()?;
+    if skip_verify {
+        policy.check_skip()?;
+    }
+    
+    // An archive downloaded earlier needs no network
+    if let Some(cached) = cache::find(version, selector) {
+        let signature = cache::signature(&cached.path);
+        if policy.required() && signature.is_none() {
+            // Cached before signatures were required; fetch it again with its signature
+            println!(
+                "{} Cached {} has no signature; downloading again",
+                "Note:".yellow(),
+                cached.asset
+            );
+        } else {
+            println!("Using cached {} ({})", cached.asset, version);
+            if !skip_verify {
+                policy.verify(&cached.path, &cached.asset, signature.as_deref())?;
+            }
+            let is_tarball = archive::archive_kind(&cached.asset).unwrap_or(true);
+            let info = InstallInfo::new(
+                InstallMethod::Cache,
+                Some(cached.path.display().to_string()),
+                Some(&cached.asset),
+                cache::checksum(&cached.path),
+            );
+            extract_atomically(&cached.path, dest, is_tarball, info)?;
+            cache::touch(&cached.path);
+            println!("{} Extraction complete", "✓".green());
+            return Ok(());
+        }
+    }
+    
+    let client = http::client()?;
+    
+    // The asset list decides which archive fits this platform; with a token,
+    // assets are also fetched through the API (needed for private repositories)
+    let release_info = release::fetch_release(client, source, version).await?;
+    let asset_name = selector.select(release_info.as_ref())?;
+    let is_tarball = archive::archive_kind(&asset_name).with_context(|| {
+        format!("Unsupported archive type: {} (expected .tar.gz or .zip)", asset_name)
+    })?;
+    
+    let download_url = source.asset_download_url(release_info.as_ref(), version, &asset_name);
+    
+    // Download to temp file next to the version directory so an interrupted
+    // download can be resumed on the next run
+    let temp_path = dest.with_file_name(partial_download_name(version, &asset_name));
+    if let Some(parent) = temp_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    
+    // Fetch only what changed since an installed version, unless a full download is half done
+    if !temp_path.exists() {
+        let verify = (!skip_verify).then_some(&policy);
+        match try_delta_install(client, source, release_info.as_ref(), verify, version, &asset_name, dest).await {
+            Ok(true) => {
+                println!("{} Delta upgrade complete", "✓".green());
+                return Ok(());
+            }
+            Ok(false) => {}
+            Err(e) => println!(
+                "{} Delta upgrade failed: {:#}\nDownloading the full archive instead",
+                "Warning:".yellow(),
+                e
+            ),
+        }
+    }
+    
+    println!("Downloading {} ({})...", asset_name, version);
+    
+    download_with_retry(client, source, &download_url, &temp_path).await?;
+    
+    // Verify integrity and origin before touching the install directory
+    let (checksum, signature) = if skip_verify {
+        println!(
+            "{} Skipping checksum and signature verification (--skip-verify)",
+            "Warning:".yellow()
+        );
+        (None, None)
+    } else {
+        let (expected, signature) = verify_download(
+            client,
+            source,
+            release_info.as_ref(),
+            version,
+            &asset_name,
+            &temp_path,
+            &policy,
+        )
+        .await?;
+        (Some(expected), signature)
+    };
+    
+    println!("Extracting...");
+    let sha256 = match &checksum {
+        Some(hash) => hash.clone(),
+        None => manifest::sha256_file(&temp_path)?,
+    };
+    let info = InstallInfo::new(
+        InstallMethod::Download,
+        Some(download_url),
+        Some(&asset_name),
+        Some(sha256),
+    );
+    extract_atomically(&temp_path, dest, is_tarball, info)?;
+    
+    // Keep the archive for reinstalls; it is only a convenience, so failing to cache is not fatal.
+    // Cached archives are reused without the network, so only verified ones are kept.
+    match &checksum {
+        Some(checksum) => {
+            if let Err(e) = cache::store(&temp_path, version, &asset_name, checksum, signature.as_deref()) {
+                println!("{} Could not cache the archive: {:#}", "Warning:".yellow(), e);
+                let _ = fs::remove_file(&temp_path);
+            }
+        }
+        None => {
+            let _ = fs::remove_file(&temp_path);
+        }
+    }
+    
+    println!("{} Extraction complete", "✓".green());
+    Ok(())
+}
+
+/// Check a downloaded asset against its published checksum and, when a key is
+/// configured, its signature. Returns both for the cache.
+async fn verify_download(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    release_info: Option<&release::ReleaseInfo>,
+    version: &str,
+    asset_name: &str,
+    path: &Path,
+    policy: &SignaturePolicy,
+) -> Result<(String, Option<String>)> {
+    let expected = fetch_expected_checksum(client, source, release_info, version, asset_name).await?;
+    let signature = if policy.is_active() {
+        let signature_name = signature::signature_name(asset_name);
+        let url = source.asset_download_url(release_info, version, &signature_name);
+        release::fetch_optional_text(client, source, &url).await?
+    } else {
+        None
+    };
+    let verified = verify_checksum(path, asset_name, &expected)
+        .and_then(|_| policy.verify(path, asset_name, signature.as_deref()));
+    if let Err(e) = verified {
+        // A corrupted or tampered partial file must not be resumed
+        let _ = fs::remove_file(path);
+        return Err(e);
+    }
+    Ok((expected, signature))
+}
+
+/// Assemble `dest` from files installed versions already have plus a delta
+/// archive with the changed ones, when the release publishes a file manifest
+/// for `asset_name`. Returns false when there is nothing to reuse or no delta,
+/// leaving the full download to the caller. `verify` is None with `--skip-verify`.
+async fn try_delta_install(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    release_info: Option<&release::ReleaseInfo>,
+    verify: Option<&SignaturePolicy>,
+    version: &str,
+    asset_name: &str,
+    dest: &Path,
+) -> Result<bool> {
+    if config::installed_versions().is_empty() {
+        return Ok(false);
+    }
+    
+    // Without an asset list (static mirrors) every file has to be asked for
+    let published = |name: &str| {
+        release_info
+            .filter(|r| !r.assets.is_empty())
+            .is_none_or(|r| r.has_asset(name))
+    };
+    let manifest_name = delta::manifest_name(asset_name);
+    if !published(&manifest_name) {
+        return Ok(false);
+    }
+    let manifest_url = source.asset_download_url(release_info, version, &manifest_name);
+    let Some(text) = release::fetch_optional_text(client, source, &manifest_url).await? else {
+        return Ok(false);
+    };
+    
+    // Scratch space for the manifest and the delta; a crashed run leaves it to the stale staging cleanup
+    let work = dest.with_file_name(format!(
+        "{}{}-delta-{}",
+        STAGING_PREFIX,
+        version,
+        std::process::id()
+    ));
+    if work.exists() {
+        fs::remove_dir_all(&work)?;
+    }
+    fs::create_dir_all(&work)
+        .with_context(|| format!("Failed to create {}", work.display()))?;
+    
+    let result = async {
+        // The manifest decides what gets installed, so it is verified like the archive itself
+        if let Some(policy) = verify {
+            let manifest_path = work.join(&manifest_name);
+            fs::write(&manifest_path, &text)?;
+            let expected =
+                fetch_expected_checksum(client, source, release_info, version, &manifest_name).await?;
+            verify_checksum(&manifest_path, &manifest_name, &expected)?;
+            let signature = if policy.is_active() {
+                let url = source.asset_download_url(
+                    release_info,
+                    version,
+                    &signature::signature_name(&manifest_name),
+                );
+                release::fetch_optional_text(client, source, &url).await?
+            } else {
+                None
+            };
+            policy.verify(&manifest_path, &manifest_name, signature.as_deref())?;
+        }
+        let target: Manifest = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", manifest_name))?;
+        
+        let plan = delta::plan(&target);
+        let Some(base) = plan.base.clone() else {
+            return Ok(false);
+        };
+        
+        let delta_dir = if plan.missing.is_empty() {
+            None
+        } else {
+            let delta_name = delta::delta_name(asset_name, &base);
+            if !published(&delta_name) {
+                println!(
+                    "{} Release {} has no delta from {}",
+                    "Note:".yellow(),
+                    version,
+                    base
+                );
+                return Ok(false);
+            }
+            println!(
+                "Downloading {} ({} changed file(s), {})...",
+                delta_name,
+                plan.missing.len(),
+                indicatif::HumanBytes(plan.missing_bytes)
+            );
+            let archive_path = work.join(&delta_name);
+            let url = source.asset_download_url(release_info, version, &delta_name);
+            download_with_retry(client, source, &url, &archive_path).await?;
+            
+            // Its files are checked against the manifest once assembled
+            let files = work.join("files");
+            let is_tarball = archive::archive_kind(&delta_name).unwrap_or(true);
+            archive::extract(&archive_path, &files, is_tarball)?;
+            Some(files)
+        };
+        
+        println!(
+            "Reusing {} unchanged file(s) ({}) from installed versions",
+            plan.reused,
+            indicatif::HumanBytes(plan.reused_bytes)
+        );
+        let mut info =
+            InstallInfo::new(InstallMethod::Delta, Some(manifest_url), Some(asset_name), None);
+        info.base = Some(base);
+        install_atomically(dest, info, |staging| {
+            delta::assemble(&target, &plan, delta_dir.as_deref(), staging)
+        })?;
+        Ok(true)
+    }
+    .await;
+    
+    let _ = fs::remove_dir_all(&work);
+    result
+}
+
+/// Prefix of the staging directories installs are extracted into
+const STAGING_PREFIX: &str = ".staging-";
+
+/// Temporary files of atomic writes untouched for this long belong to a crashed run.
+/// Newer ones may be in use: the release list and update check caches are written without the lock.
+const STALE_TEMP_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Partial downloads untouched for this long are not worth resuming
+const STALE_DOWNLOAD_AGE: std::time::Duration = std::time::Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Extract into a staging directory next to `dest` and rename it into place only
+/// once extraction succeeded, so a failed install never leaves a half-populated version.
+/// The integrity manifest and the install record `info` are written before the rename.
+fn extract_atomically(archive: &Path, dest: &Path, is_tarball: bool, info: InstallInfo) -> Result<()> {
+    install_atomically(dest, info, |staging| archive::extract(archive, staging, is_tarball))
+}
+
+/// Let `fill` populate a staging directory next to `dest`, then write the
+/// manifest and install record and rename the directory into place
+fn install_atomically(
+    dest: &Path,
+    info: InstallInfo,
+    fill: impl FnOnce(&Path) -> Result<()>,
+) -> Result<()> {
+    let name = dest.file_name().unwrap_or_default().to_string_lossy();
+    let staging = dest.with_file_name(format!(
+        "{}{}-{}",
+        STAGING_PREFIX,
+        name,
+        std::process::id()
+    ));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    
+    let result = fill(&staging)
+        .and_then(|_| {
+            let missing = manifest::missing_layout(&staging);
+            if !missing.is_empty() {
+                println!(
+                    "{} The SDK archive lacks: {}",
+                    "Warning:".yellow(),
+                    missing.join(", ")
+                );
+            }
+            let manifest = Manifest::build(&staging, &name, info.asset.as_deref())?;
+            manifest.save(&staging)?;
+            InstallInfo {
+                size: manifest.files.iter().map(|f| f.size).sum(),
+                ..info
+            }
+            .save(&staging)
+        })
+        .and_then(|_| {
+            fs::rename(&staging, dest)
+                .with_context(|| format!("Failed to move SDK into {}", dest.display()))
+        });
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+/// File an interrupted download of `asset` is kept in for resuming. It is named
+/// after the asset too, so a run selecting another asset never resumes it.
+fn partial_download_name(version: &str, asset: &str) -> String {
+    format!("{}-{}.tmp", version, asset)
+}
+
+/// Whether `name` is the partial download of `asset` for `version`, in the current
+/// naming or the older `<version>.<ext>.tmp`
+fn is_partial_download_of(name: &str, version: &str, asset: Option<&str>) -> bool {
+    asset.is_some_and(|asset| name == partial_download_name(version, asset))
+        || name == format!("{}.tar.gz.tmp", version)
+        || name == format!("{}.zip.tmp", version)
+}
+
+/// Remove staging directories, temporary files and partial downloads left behind by crashed runs.
+/// Partial downloads are kept for resuming unless their version is already
+/// installed or they have not been touched for a long time.
+fn cleanup_stale_install_files() {
+    let Ok(root) = config::store_root(config::selected_store()) else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(&root) else {
+        return;
+    };
+    
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let age = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .unwrap_or_default();
+        
+        if name.starts_with(REMOVING_PREFIX) && path.is_dir() {
+            // Interrupted `remove`/`gc`; the version is already gone
+            let _ = fs::remove_dir_all(&path);
+        } else if name.starts_with(STAGING_PREFIX) && path.is_dir() {
+            // Callers hold the SDK lock, so no other install is using it
+            if fs::remove_dir_all(&path).is_ok() {
+                println!("{} Removed stale staging directory {}", "Note:".yellow(), name);
+            }
+        } else if !name.starts_with('.')
+            && name
+                .strip_suffix(".tmp")
+                .is_some_and(|archive| archive::archive_kind(archive).is_some())
+        {
+            let installed = config::versions_in(&root).iter().any(|version| {
+                let asset = Manifest::load(&root.join(version))
+                    .ok()
+                    .flatten()
+                    .and_then(|m| m.asset);
+                is_partial_download_of(&name, version, asset.as_deref())
+            });
+            if (installed || age >= STALE_DOWNLOAD_AGE) && fs::remove_file(&path).is_ok() {
+                println!("{} Removed orphaned partial download {}", "Note:".yellow(), name);
+            }
+        } else if name.starts_with('.')
+            && name.ends_with(".tmp")
+            && path.is_file()
+            && age >= STALE_TEMP_AGE
+        {
+            // Temporary file of an interrupted atomic write (dev.json, current.txt, ...)
+            let _ = fs::remove_file(&path);
+        }
+    }
+}
+
+/// Compare the SHA-256 of `path` with the published value
+fn verify_checksum(path: &Path, name: &str, expected: &str) -> Result<()> {
+    let actual = manifest::sha256_file(path)?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!(
+            "Checksum mismatch for {}\n  expected: {}\n  actual:   {}\nThe archive is corrupted; refusing to install.",
+            name,
+            expected,
+            actual
+        );
+    }
+    println!("{} Checksum verified (SHA-256)", "✓".green());
+    Ok(())
+}
+
+/// Maximum number of attempts for a single download
+const DOWNLOAD_MAX_ATTEMPTS: u32 = 5;
+
+/// Outcome of a failed download attempt
+enum DownloadError {
+    /// Network hiccup or server-side error; worth retrying
+    Transient(anyhow::Error),
+    /// Retrying will not help (e.g. 404)
+    Fatal(anyhow::Error),
+}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(e: std::io::Error) -> Self {
+        DownloadError::Fatal(e.into())
+    }
+}
+
+/// Download `url` to `dest`, resuming any partial file already there and
+/// retrying transient failures with exponential backoff.
+/// The partial file is kept on failure so a later run can pick it up.
+async fn download_with_retry(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    url: &str,
+    dest: &Path,
+) -> Result<()> {
+    // Local mirror: a plain copy, nothing to resume or retry
+    if let Some(src) = release::file_url_path(url) {
+        fs::copy(&src, dest)
+            .with_context(|| format!("Failed to copy {}", src.display()))?;
+        return Ok(());
+    }
+    
+    let mut attempt = 1;
+    loop {
+        match download_attempt(client, source, url, dest).await {
+            Ok(()) => return Ok(()),
+            Err(DownloadError::Transient(e)) if attempt < DOWNLOAD_MAX_ATTEMPTS => {
+                let delay = std::time::Duration::from_secs(1 << (attempt - 1));
+                println!(
+                    "{} Download interrupted ({}), retrying in {}s ({}/{})",
+                    "Warning:".yellow(),
+                    e,
+                    delay.as_secs(),
+                    attempt,
+                    DOWNLOAD_MAX_ATTEMPTS - 1
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(DownloadError::Transient(e)) | Err(DownloadError::Fatal(e)) => {
+                return Err(e.context(format!("Failed to download {}", url)));
+            }
+        }
+    }
+}
+
+/// Single download attempt, using an HTTP Range request when `dest` already
+/// holds part of the file
+async fn download_attempt(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    url: &str,
+    dest: &Path,
+) -> std::result::Result<(), DownloadError> {
+    let existing = fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+    
+    let mut request = source.get(client, url);
+    if existing > 0 {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", existing));
+    }
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| DownloadError::Transient(e.into()))?;
+    
+    // Waiting out a rate limit takes far longer than the retry backoff
+    if let Some(e) = release::rate_limit_error(&resp, source.sends_token(url)) {
+        return Err(DownloadError::Fatal(e));
+    }
+    
+    let status = resp.status();
+    if status == reqwest::StatusCode::PARTIAL_CONTENT && content_range_start(&resp) != Some(existing) {
+        // Appending would corrupt the file; start over from the first byte
+        File::create(dest)?;
+        return Err(DownloadError::Transient(anyhow::anyhow!(
+            "server did not resume at byte {}, restarting the download",
+            existing
+        )));
+    }
+    let (mut file, start) = if status == reqwest::StatusCode::PARTIAL_CONTENT {
+        println!("  Resuming from {} bytes", existing);
+        (fs::OpenOptions::new().append(true).open(dest)?, existing)
+    } else if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE && existing > 0 {
+        // Nothing left to fetch; the checksum decides whether the file is good
+        return Ok(());
+    } else if status.is_success() {
+        // Server ignored the Range header (or there was nothing to resume)
+        (File::create(dest)?, 0)
+    } else if status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+    {
+        return Err(DownloadError::Transient(anyhow::anyhow!("server returned {}", status)));
+    } else {
+        return Err(DownloadError::Fatal(anyhow::anyhow!("server returned {}", status)));
+    };
+    
+    let total_size = start + resp.content_length().unwrap_or(0);
+    
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+            .map_err(|e| DownloadError::Fatal(e.into()))?
+            .progress_chars("#>-"),
+    );
+    pb.set_position(start);
+    
+    let mut downloaded = start;
+    let mut stream = resp.bytes_stream();
+    
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| DownloadError::Transient(e.into()))?;
+        file.write_all(&chunk)?;
+        downloaded += chunk.len() as u64;
+        pb.set_position(downloaded);
+    }
+    file.flush()?;
+    
+    pb.finish_with_message("Downloaded");
+    Ok(())
+}
+
+/// First byte of a 206 response, from its `Content-Range: bytes <start>-<end>/<total>` header
+fn content_range_start(resp: &reqwest::Response) -> Option<u64> {
+    let range = resp.headers().get(reqwest::header::CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.trim().strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+/// Look up the published SHA-256 of `asset_name` for a release.
+/// Tries the release-wide SHA256SUMS file first, then a per-asset `.sha256` file.
+async fn fetch_expected_checksum(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    release_info: Option<&release::ReleaseInfo>,
+    version: &str,
+    asset_name: &str,
+) -> Result<String> {
+    let sums_url = source.asset_download_url(release_info, version, CHECKSUMS_FILE);
+    if let Some(content) = release::fetch_optional_text(client, source, &sums_url).await? {
+        if let Some(hash) = parse_checksum_list(&content, asset_name) {
+            return Ok(hash);
+        }
+    }
+    
+    let asset_sum_name = format!("{}.sha256", asset_name);
+    let asset_sum_url = source.asset_download_url(release_info, version, &asset_sum_name);
+    if let Some(content) = release::fetch_optional_text(client, source, &asset_sum_url).await? {
+        if let Some(hash) = content.split_whitespace().next().filter(|h| is_sha256(h)) {
+            return Ok(hash.to_string());
+        }
+    }
+    
+    bail!(
+        "No checksum published for {} in release {}. Re-run with --skip-verify to install without verification.",
+        asset_name,
+        version
+    )
+}
+
+/// Find the hash for `file_name` in `sha256sum`-style output ("<hash>  <name>" or "<hash> *<name>").
+/// Lines that are not a SHA-256 followed by a name are skipped.
+fn parse_checksum_list(content: &str, file_name: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next().filter(|h| is_sha256(h))?;
+        let name = parts.next()?.trim_start_matches('*');
+        (name == file_name && parts.next().is_none()).then(|| hash.to_string())
+    })
+}
+
+/// Whether `text` looks like a hex-encoded SHA-256
+fn is_sha256(text: &str) -> bool {
+    text.len() == 64 && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Versions command: list installed versions, or remote releases with `--remote`.
+/// `--long` adds each install's record and disk usage, `--json` prints them as JSON.
+pub async fn versions(
+    source: &ReleaseSource,
+    remote: bool,
+    offline: bool,
+    long: bool,
+    json: bool,
+) -> Result<()> {
+    if remote {
+        return remote_versions(source, offline).await;
+    }
+    
+    let versions = config::installed_versions();
+    let current = config::current_version();
+    
+    if json {
+        let entries = versions
+            .iter()
+            .map(|v| version_json(v, Some(v) == current.as_ref()))
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    
+    if versions.is_empty() {
+        println!("No SDK versions installed.");
+        println!("Run {} to install.", "mpf-dev setup".cyan());
+        return Ok(());
+    }
+    
+    println!("{}", "Installed SDK versions:".bold());
+    for v in &versions {
+        let stores = config::version_stores(v)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut label = format!("[{}]", stores);
+        if let Ok(dir) = config::version_dir(v) {
+            let companions = Companion::installed(&dir);
+            if !companions.is_empty() {
+                let names: Vec<String> = companions.iter().map(|c| c.to_string()).collect();
+                label.push_str(&format!(" +{}", names.join(", ")));
+            }
+        }
+        let label = label.dimmed();
+        if Some(v) == current.as_ref() {
+            println!("  {} {} {} {}", "*".green(), v.green(), "(current)".dimmed(), label);
+        } else {
+            println!("    {} {}", v, label);
+        }
+        if long {
+            if let Ok(dir) = config::version_dir(v) {
+                print_install_details(&dir, "      ");
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Install record, size and location of a version directory, one field per line
+fn print_install_details(dir: &Path, indent: &str) {
+    let info = InstallInfo::load(dir);
+    match &info {
+        Some(info) => {
+            let age = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs().saturating_sub(info.installed_at) / 86400)
+                .unwrap_or(0);
+            let method = match &info.base {
+                Some(base) => format!("{} from {}", info.method, base),
+                None => info.method.to_string(),
+            };
+            println!("{}Installed: {} day(s) ago ({})", indent, age, method);
+        }
+        None => println!(
+            "{}{}",
+            indent,
+            "No install record (installed by an older mpf-dev)".dimmed()
+        ),
+    }
+    if let Some(size) = installed_size(dir, info.as_ref()) {
+        println!("{}Size:      {}", indent, indicatif::HumanBytes(size));
+    }
+    // SDK files no other version shares, counted like Size
+    println!(
+        "{}Unshared:  {}",
+        indent,
+        indicatif::HumanBytes(dedupe::sdk_reclaimable_size(dir))
+    );
+    if let Some(info) = &info {
+        if let Some(asset) = &info.asset {
+            println!("{}Asset:     {}", indent, asset);
+        }
+        if let Some(sha256) = &info.sha256 {
+            println!("{}SHA-256:   {}", indent, sha256);
+        }
+        if let Some(source) = &info.source {
+            println!("{}Source:    {}", indent, source);
+        }
+    }
+    println!("{}Location:  {}", indent, dir.display());
+}
+
+/// Size of the SDK files: recorded at install, else summed from the manifest
+fn installed_size(dir: &Path, info: Option<&InstallInfo>) -> Option<u64> {
+    info.map(|i| i.size).or_else(|| {
+        Manifest::load(dir)
+            .ok()
+            .flatten()
+            .map(|m| m.files.iter().map(|f| f.size).sum())
+    })
+}
+
+/// `versions --json` entry for one installed version
+fn version_json(version: &str, current: bool) -> serde_json::Value {
+    let dir = config::version_dir(version).ok();
+    let info = dir.as_deref().and_then(InstallInfo::load);
+    let companions: Vec<&str> = dir
+        .as_deref()
+        .map(Companion::installed)
+        .unwrap_or_default()
+        .into_iter()
+        .map(Companion::token)
+        .collect();
+    serde_json::json!({
+        "version": version,
+        "current": current,
+        "stores": config::version_stores(version).iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        "path": dir.as_ref().map(|d| d.display().to_string()),
+        "method": info.as_ref().map(|i| i.method),
+        "source": info.as_ref().and_then(|i| i.source.clone()),
+        "asset": info.as_ref().and_then(|i| i.asset.clone()),
+        "sha256": info.as_ref().and_then(|i| i.sha256.clone()),
+        "base": info.as_ref().and_then(|i| i.base.clone()),
+        "installed_at": info.as_ref().map(|i| i.installed_at),
+        "size": dir.as_deref().and_then(|d| installed_size(d, info.as_ref())),
+        "unshared_size": dir.as_deref().map(dedupe::sdk_reclaimable_size),
+        "companions": companions,
+    })
+}
+
+/// List every release from the release source
+async fn remote_versions(source: &ReleaseSource, offline: bool) -> Result<()> {
+    let index = load_release_index(source, offline, true).await?;
+    let installed = config::installed_versions();
+    let current = config::current_version();
+    let selector = AssetSelector::default();
+    
+    if index.releases.is_empty() {
+        println!("No releases found in {}", source.repo);
+        return Ok(());
+    }
+    
+    let mut releases: Vec<&release::ReleaseInfo> = index.releases.iter().collect();
+    releases.sort_by(|a, b| version::compare_versions(&b.tag, &a.tag));
+    
+    println!("{} {}", "Releases from".bold(), source.repo.bold());
+    for r in releases {
+        let marker = if Some(&r.tag) == current.as_ref() {
+            "*".green()
+        } else if installed.contains(&r.tag) {
+            "+".cyan()
+        } else {
+            " ".normal()
+        };
+        
+        let mut notes = Vec::new();
+        if r.prerelease {
+            notes.push("prerelease".yellow().to_string());
+        }
+        if !r.assets.is_empty() && selector.select(Some(r)).is_err() {
+            notes.push(format!("no {} asset", selector.describe()).red().to_string());
+        }
+        if Some(&r.tag) == current.as_ref() {
+            notes.push("current".green().to_string());
+        } else if installed.contains(&r.tag) {
+            notes.push("installed".cyan().to_string());
+        }
+        
+        println!(
+            "  {} {:<16} {}  {}",
+            marker,
+            r.tag,
+            r.date().dimmed(),
+            notes.join(", ")
+        );
+    }
+    
+    Ok(())
+}
+
+/// Get the release index: fetched fresh (and cached) when online and `refresh`
+/// is set, otherwise from the cache. Falls back to the cache when the fetch fails.
+async fn load_release_index(
+    source: &ReleaseSource,
+    offline: bool,
+    refresh: bool,
+) -> Result<release::ReleaseIndex> {
+    let cached = release::ReleaseIndex::load_cached().filter(|i| i.matches(source));
+    
+    if offline || (!refresh && cached.is_some()) {
+        return cached.context(
+            "No cached release index. Run `mpf-dev versions --remote` while online first.",
+        );
+    }
+    
+    let client = http::client()?;
+    match release::ReleaseIndex::fetch(client, source).await {
+        Ok(index) => {
+            index.save()?;
+            Ok(index)
+        }
+        Err(e) => match cached {
+            Some(index) => {
+                println!(
+                    "{} Could not refresh release list ({}); showing cached copy",
+                    "Warning:".yellow(),
+                    e
+                );
+                Ok(index)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Changelog command: print release notes for versions in `<from>..<to>`
+/// (exclusive of `from`, inclusive of `to`; an empty `to` means the newest release)
+pub async fn changelog(source: &ReleaseSource, range: &str, offline: bool) -> Result<()> {
+    let (from, to) = range
+        .split_once("..")
+        .context("Expected a version range like 1.0.0..1.2.0")?;
+    let from = version::normalize(from.trim());
+    let to = (!to.trim().is_empty()).then(|| version::normalize(to.trim()));
+    
+    let mut index = load_release_index(source, offline, false).await?;
+    
+    // The cache may predate the requested release
+    let missing = to.as_ref().is_some_and(|t| index.find(t).is_none());
+    if missing && !offline {
+        index = load_release_index(source, false, true).await?;
+    }
+    if let Some(t) = &to {
+        if index.find(t).is_none() {
+            bail!("Release {} not found in the release index", t);
+        }
+    }
+    
+    let releases = releases_between(&index, &from, to.as_deref());
+    if releases.is_empty() {
+        println!("No releases between {} and {}", from, to.as_deref().unwrap_or("latest"));
+        return Ok(());
+    }
+    print_release_notes(&releases);
+    
+    Ok(())
+}
+
+/// Releases newer than `from` up to and including `to` (or the newest), newest first
+fn releases_between<'a>(
+    index: &'a release::ReleaseIndex,
+    from: &str,
+    to: Option<&str>,
+) -> Vec<&'a release::ReleaseInfo> {
+    let mut releases: Vec<&release::ReleaseInfo> = index
+        .releases
+        .iter()
+        .filter(|r| version::compare_versions(&r.tag, from).is_gt())
+        .filter(|r| to.is_none_or(|t| version::compare_versions(&r.tag, t).is_le()))
+        .collect();
+    releases.sort_by(|a, b| version::compare_versions(&b.tag, &a.tag));
+    releases
+}
+
+fn print_release_notes(releases: &[&release::ReleaseInfo]) {
+    for r in releases {
+        let title = r.name.as_deref().unwrap_or(&r.tag);
+        println!("{} {} {}", "##".cyan(), title.bold(), format!("({})", r.date()).dimmed());
+        println!();
+        println!("{}", r.body.as_deref().unwrap_or("(no release notes)").trim());
+        println!();
+    }
+}
+
+/// Update command: move the current SDK to the newest compatible release
+/// (same major version unless `major` is set) in the channel
+pub async fn update(
+    source: &ReleaseSource,
+    channel: Option<Channel>,
+    major: bool,
+    check: bool,
+) -> Result<()> {
+    let current = config::current_version()
+        .context("No SDK version is set. Run `mpf-dev setup` first.")?;
+    let channel = channel.unwrap_or_else(configured_channel);
+    let selector = AssetSelector::default();
+    
+    println!(
+        "Checking for updates to {} ({} channel)...",
+        current.cyan(),
+        channel.name()
+    );
+    let index = load_release_index(source, false, true).await?;
+    
+    let newest = VersionSpec::Latest.resolve(installable_tags(&index, &selector), channel, None)?;
+    let compatible = match version::parse_version(&current) {
+        Some(v) if !major => {
+            let req = semver::VersionReq::parse(&format!("^{}", v))?;
+            VersionSpec::Range(req).resolve(installable_tags(&index, &selector), channel, None)?
+        }
+        _ => newest.clone(),
+    };
+    
+    let target = compatible.filter(|t| version::compare_versions(t, &current).is_gt());
+    let Some(target) = target else {
+        println!("{} SDK {} is up to date", "✓".green(), current);
+        if let Some(newest) = newest.filter(|n| version::compare_versions(n, &current).is_gt()) {
+            println!(
+                "{} {} is available (new major version); run `mpf-dev update --major` to switch",
+                "Note:".yellow(),
+                newest
+            );
+        }
+        return Ok(());
+    };
+    
+    println!("Update available: {} → {}", current, target.green());
+    println!();
+    let notes: Vec<&release::ReleaseInfo> = releases_between(&index, &current, Some(&target))
+        .into_iter()
+        .filter(|r| version::parse_version(&r.tag).is_none_or(|v| channel.accepts(&v)))
+        .collect();
+    print_release_notes(&notes);
+    
+    if check {
+        println!("Run `mpf-dev update` to install it.");
+        return Ok(());
+    }
+    
+    // The new version gets the debugging aids the current one has
+    let mut selector = selector;
+    if let Ok(dir) = config::version_dir(&current) {
+        selector.companions = Companion::installed(&dir);
+    }
+    setup(source, Some(target), channel, None, selector, false, false).await
+}
+
+/// Release channel from the `update` section of dev.json
+fn configured_channel() -> Channel {
+    DevConfig::load()
+        .ok()
+        .and_then(|c| c.update)
+        .and_then(|u| u.channel)
+        .unwrap_or_default()
+}
+
+/// How long a "new SDK available" check result is reused
+const UPDATE_CHECK_INTERVAL_SECS: u64 = 24 * 60 * 60;
+
+/// Longest the notice may delay `status` or `run`
+const UPDATE_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Print a note when a newer SDK than the active one exists. The lookup runs
+/// at most once a day (cached in ~/.mpf-sdk/update-check.json), never fails
+/// the command and can be turned off with `"update": { "notify": false }`.
+pub async fn update_notice(source: &ReleaseSource) {
+    let update_config = DevConfig::load().ok().and_then(|c| c.update).unwrap_or_default();
+    if !update_config.notify.unwrap_or(true) {
+        return;
+    }
+    // The version this directory uses, pins included; a broken pin is reported elsewhere
+    let Some((current, from)) = config::active_version().ok().flatten() else {
+        return;
+    };
+    let channel = update_config.channel.unwrap_or_default();
+    let now = release::unix_now();
+    
+    let cached = release::UpdateCheck::load().filter(|c| {
+        c.matches(source, channel) && now.saturating_sub(c.checked_at) < UPDATE_CHECK_INTERVAL_SECS
+    });
+    let latest = match cached {
+        Some(check) => check.latest,
+        None => {
+            let latest = tokio::time::timeout(UPDATE_CHECK_TIMEOUT, newest_release(source, channel))
+                .await
+                .ok()
+                .and_then(|r| r.ok())
+                .flatten();
+            // Failed checks are recorded too, so an offline machine is not slowed down every time
+            let _ = release::UpdateCheck::new(source, channel, now, latest.clone()).save();
+            latest
+        }
+    };
+    
+    if let Some(latest) = latest.filter(|l| version::compare_versions(l, &current).is_gt()) {
+        eprintln!();
+        match from {
+            config::VersionSource::Global => eprintln!(
+                "{} SDK {} is available (current: {}). Run `mpf-dev update` to upgrade.",
+                "Note:".yellow(),
+                latest.green(),
+                current
+            ),
+            from => eprintln!(
+                "{} SDK {} is available (using {}, set by {}).",
+                "Note:".yellow(),
+                latest.green(),
+                current,
+                from
+            ),
+        }
+    }
+}
+
+/// Newest release in the channel, without any output
+async fn newest_release(source: &ReleaseSource, channel: Channel) -> Result<Option<String>> {
+    if channel == Channel::Stable {
+        return fetch_latest_version(source).await.map(Some);
+    }
+    let index = release::ReleaseIndex::fetch(http::client()?, source).await?;
+    let _ = index.save();
+    VersionSpec::Latest.resolve(installable_tags(&index, &AssetSelector::default()), channel, None)
+}
+
+/// Exit code of `self-update --check` when a newer mpf-dev exists
+const SELF_UPDATE_AVAILABLE_EXIT_CODE: i32 = 10;
+
+/// Self-update command: replace the running mpf-dev with its newest release,
+/// keeping the previous executable for `--rollback`
+pub async fn self_update(check: bool, rollback: bool) -> Result<()> {
+    let current = version::normalize(env!("CARGO_PKG_VERSION"));
+    let exe = env::current_exe().context("Could not locate the running mpf-dev executable")?;
+    
+    if rollback {
+        return self_update_rollback(&exe, &current);
+    }
+    
+    let tool = self_update::tool_source();
+    let client = http::client()?;
+    println!("Checking {} for mpf-dev updates...", tool.repo);
+    let value = release::fetch_json(client, &tool, &tool.latest_release_url()).await?;
+    let release_info =
+        release::ReleaseInfo::from_api(&value).context("Could not find latest mpf-dev release")?;
+    let latest = release_info.tag.clone();
+    
+    if !version::compare_versions(&latest, &current).is_gt() {
+        println!("{} mpf-dev {} is up to date", "✓".green(), current);
+        return Ok(());
+    }
+    if check {
+        println!("mpf-dev {} is available (current: {})", latest, current);
+        std::process::exit(SELF_UPDATE_AVAILABLE_EXIT_CODE);
+    }
+    
+    let asset_name = self_update::asset_name();
+    if !release_info.assets.is_empty() && !release_info.has_asset(&asset_name) {
+        bail!(
+            "Release {} has no {} asset for this platform",
+            latest,
+            asset_name
+        );
+    }
+    
+    let work_dir = env::temp_dir().join(format!("mpf-dev-self-update-{}", std::process::id()));
+    fs::create_dir_all(&work_dir)?;
+    let result = async {
+        let archive_path = work_dir.join(&asset_name);
+        let url = tool.asset_download_url(Some(&release_info), &latest, &asset_name);
+        println!("Downloading {} ({})...", asset_name, latest);
+        download_with_retry(client, &tool, &url, &archive_path).await?;
+        
+        let expected =
+            fetch_expected_checksum(client, &tool, Some(&release_info), &latest, &asset_name).await?;
+        verify_checksum(&archive_path, &asset_name, &expected)?;
+        
+        let binary = work_dir.join("mpf-dev.bin");
+        let is_tarball = archive::archive_kind(&asset_name).unwrap_or(true);
+        self_update::extract_binary(&archive_path, is_tarball, &binary)?;
+        let reported = self_update::check_binary(&binary)?;
+        
+        let staged = self_update::stage(&exe, &binary)?;
+        self_update::save_backup(&exe, &current)?;
+        self_update::replace(&exe, &staged)?;
+        Ok::<_, anyhow::Error>(reported)
+    }
+    .await;
+    let _ = fs::remove_dir_all(&work_dir);
+    let reported = result?;
+    
+    println!("{} Updated mpf-dev {} → {} ({})", "✓".green(), current, latest, reported);
+    println!("  Undo with `mpf-dev self-update --rollback`");
+    Ok(())
+}
+
+/// Swap the running executable with the backup from the last self-update
+fn self_update_rollback(exe: &Path, current: &str) -> Result<()> {
+    let backup = self_update::backup_path()?;
+    if !backup.is_file() {
+        bail!("No previous mpf-dev to roll back to ({} does not exist)", backup.display());
+    }
+    let previous = self_update::backup_version().unwrap_or_else(|| "previous version".to_string());
+    
+    // Stage the backup first: saving the running executable overwrites it
+    let staged = self_update::stage(exe, &backup)?;
+    self_update::save_backup(exe, current)?;
+    self_update::replace(exe, &staged)?;
+    
+    println!("{} Rolled back mpf-dev {} → {}", "✓".green(), current, previous);
+    Ok(())
+}
+
+/// Use command: switch SDK version, globally or (`local`) for the project in the current directory
+pub fn use_version(version: &str, channel: Channel, local: bool) -> Result<()> {
+    // Ranges and `latest` resolve to the highest matching installed version
+    let version_normalized = match VersionSpec::parse(version)? {
+        VersionSpec::Exact(tag) => tag,
+        spec => {
+            let installed = config::installed_versions();
+            let current = config::current_version();
+            spec.resolve(&installed, channel, current.as_deref())?
+                .with_context(|| {
+                    format!(
+                        "No installed SDK version matches '{}'. Installed: {}",
+                        version,
+                        if installed.is_empty() { "none".to_string() } else { installed.join(", ") }
+                    )
+                })?
+        }
+    };
+    
+    let version_dir = config::version_dir(&version_normalized)?;
+    
+    if local {
+        let pin = env::current_dir()?.join(config::PIN_FILE);
+        config::write_atomic(&pin, format!("{}\n", version_normalized))?;
+        register_pin_file(&pin)?;
+        println!(
+            "{} Pinned SDK {} in {}",
+            "✓".green(),
+            version_normalized,
+            pin.display()
+        );
+        if !version_dir.exists() {
+            println!(
+                "{} Version {} is not installed yet; run `mpf-dev setup --locked`",
+                "Note:".yellow(),
+                version_normalized
+            );
+        }
+        return Ok(());
+    }
+    
+    if !version_dir.exists() {
+        bail!(
+            "Version {} is not installed. Run `mpf-dev setup --version {}`",
+            version_normalized,
+            version
+        );
+    }
+    
+    config::set_current_version(&version_normalized)?;
+    
+    // Update dev.json
+    let mut dev_config = DevConfig::load().unwrap_or_default();
+    dev_config.sdk_version = Some(version_normalized.clone());
+    dev_config.save()?;
+    
+    println!(
+        "{} Now using SDK {}",
+        "✓".green(),
+        version_normalized
+    );
+    
+    Ok(())
+}
+
+/// Remove command: uninstall an SDK version
+pub fn remove(version: &str, force: bool) -> Result<()> {
+    let version_normalized = version::normalize(version);
+    let store = config::selected_store();
+    let version_dir = config::install_dir(&version_normalized)?;
+    let stores = config::version_stores(&version_normalized);
+    
+    if !version_dir.is_dir() {
+        if stores.contains(&config::Store::System) {
+            bail!(
+                "Version {} is in the system store ({}); it can only be removed with --system",
+                version_normalized,
+                config::store_root(config::Store::System)?.display()
+            );
+        }
+        bail!("Version {} is not installed", version_normalized);
+    }
+    
+    // Still usable afterwards if the other store has a copy
+    let other_copy = stores.iter().any(|s| *s != store);
+    let is_current = config::current_version().as_deref() == Some(version_normalized.as_str())
+        && !other_copy;
+    if is_current && !force {
+        bail!(
+            "Version {} is the current SDK. Switch with `mpf-dev use <version>` first, or pass --force",
+            version_normalized
+        );
+    }
+    
+    cleanup_stale_install_files();
+    let freed = remove_version_dir(&version_dir)?;
+    println!(
+        "{} Removed SDK {} ({} freed)",
+        "✓".green(),
+        version_normalized,
+        indicatif::HumanBytes(freed)
+    );
+    
+    if is_current {
+        repoint_current_version()?;
+    } else if other_copy && config::current_version().as_deref() == Some(version_normalized.as_str()) {
+        // Point the compatibility symlink at the remaining copy
+        config::set_current_version(&version_normalized)?;
+    }
+    
+    Ok(())
+}
+
+/// Gc command: remove old SDK versions, keeping the newest `keep`,
+/// the current version and any version pinned by a project
+pub fn gc(keep: usize) -> Result<()> {
+    let root = config::store_root(config::selected_store())?;
+    let versions = config::versions_in(&root);
+    
+    let current = config::current_version();
+    let pinned = pinned_versions();
+    
+    let cutoff = versions.len().saturating_sub(keep);
+    let candidates: Vec<&String> = versions[..cutoff]
+        .iter()
+        .filter(|v| Some(*v) != current.as_ref() && !pinned.contains(*v))
+        .collect();
+    
+    if candidates.is_empty() {
+        println!("Nothing to remove ({} version(s) installed).", versions.len());
+        return Ok(());
     }
     
-    // Set as current
-    config::set_current_version(&version_normalized)?;
+    cleanup_stale_install_files();
     
-    // Update dev.json
-    let mut config = DevConfig::load().unwrap_or_default();
-    config.sdk_version = Some(version_normalized.clone());
-    config.save()?;
+    let mut total_freed = 0;
+    for version in candidates {
+        let freed = remove_version_dir(&root.join(version))?;
+        total_freed += freed;
+        println!("  {} {} ({})", "-".red(), version, indicatif::HumanBytes(freed));
+    }
     
     println!(
-        "{} SDK {} installed and set as current",
+        "{} Freed {}",
         "✓".green(),
-        version_normalized
+        indicatif::HumanBytes(total_freed)
     );
-    println!("  Location: {}", sdk_root.display());
-    
     Ok(())
 }
 
-async fn fetch_latest_version() -> Result<String> {
-    let url = format!(
-        "https://api.github.com/repos/{}/releases/latest",
-        GITHUB_REPO
-    );
+/// Remember a pin file in dev.json so `gc` keeps the version it names
+fn register_pin_file(pin: &Path) -> Result<()> {
+    let pin = pin.canonicalize().unwrap_or_else(|_| pin.to_path_buf());
+    let pin = pin.to_string_lossy().to_string();
+    let mut dev_config = DevConfig::load().unwrap_or_default();
+    if !dev_config.pin_files.contains(&pin) {
+        dev_config.pin_files.push(pin);
+        dev_config.save()?;
+    }
+    Ok(())
+}
+
+/// Versions named by the project pin files mpf-dev knows about
+fn pinned_versions() -> Vec<String> {
+    let dev_config = DevConfig::load().unwrap_or_default();
     
-    let client = reqwest::Client::new();
-    let resp = client
-        .get(&url)
-        .header("User-Agent", "mpf-dev")
-        .send()
-        .await?
-        .json::<serde_json::Value>()
-        .await?;
+    let mut pin_files: Vec<PathBuf> = dev_config.pin_files.iter().map(PathBuf::from).collect();
+    pin_files.extend(config::pin_files_from_cwd());
     
-    resp["tag_name"]
-        .as_str()
-        .map(|s| s.to_string())
-        .context("Could not find latest release")
+    pin_files
+        .iter()
+        .filter_map(|p| config::read_pin_file(p).ok().flatten())
+        .map(|v| version::normalize(&v))
+        .collect()
+}
+
+/// After the current version was removed, switch to the newest remaining one
+fn repoint_current_version() -> Result<()> {
+    let remaining = config::installed_versions();
+    
+    let mut dev_config = DevConfig::load().unwrap_or_default();
+    if let Some(newest) = remaining.last() {
+        config::set_current_version(newest)?;
+        dev_config.sdk_version = Some(newest.clone());
+        println!("{} Now using SDK {}", "Note:".yellow(), newest);
+    } else {
+        config::clear_current_version()?;
+        dev_config.sdk_version = None;
+        println!("{} No SDK versions left; run `mpf-dev setup`", "Note:".yellow());
+    }
+    dev_config.save()
+}
+
+/// Prefix of version directories that are being deleted
+const REMOVING_PREFIX: &str = ".removing-";
+
+/// Delete an installed version, returning the number of bytes freed.
+/// The directory is renamed out of the way first so an interrupted delete
+/// never leaves a version that looks installed.
+fn remove_version_dir(dir: &Path) -> Result<u64> {
+    let size = dedupe::reclaimable_size(dir);
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    let trash = dir.with_file_name(format!("{}{}-{}", REMOVING_PREFIX, name, std::process::id()));
+    
+    fs::rename(dir, &trash)
+        .with_context(|| format!("Failed to remove {}", dir.display()))?;
+    fs::remove_dir_all(&trash)
+        .with_context(|| format!("Failed to remove {}", trash.display()))?;
+    Ok(size)
 }
 
-async fn download_and_extract(version: &str, dest: &PathBuf) -> Result<()> {
-    // Determine platform and asset name
-    let (asset_name, is_tarball) = if cfg!(target_os = "windows") {
-        ("mpf-windows-x64.zip".to_string(), false)
+/// `sdk verify`: compare an installed version with its manifest and optionally repair it
+pub async fn sdk_verify(source: &ReleaseSource, version: Option<String>, repair: bool) -> Result<()> {
+    let version = version
+        .map(|v| version::normalize(&v))
+        .or_else(config::current_version)
+        .context("No SDK version given and no current version set")?;
+    let dir = if config::system_store_selected() {
+        config::install_dir(&version)?
     } else {
-        ("mpf-linux-x64.tar.gz".to_string(), true)
+        config::version_dir(&version)?
     };
-    
-    let download_url = format!(
-        "https://github.com/{}/releases/download/{}/{}",
-        GITHUB_REPO, version, asset_name
-    );
-    
-    println!("Downloading {} ({})...", asset_name, version);
-    
-    let client = reqwest::Client::new();
-    let resp = client
-        .get(&download_url)
-        .header("User-Agent", "mpf-dev")
-        .send()
-        .await?;
-    
-    if !resp.status().is_success() {
+    if !dir.is_dir() {
+        bail!("SDK {} is not installed", version);
+    }
+    if repair && !dir.starts_with(config::store_root(config::selected_store())?) {
         bail!(
-            "Failed to download SDK: {} ({})",
-            resp.status(),
-            download_url
+            "SDK {} is in the read-only system store; repair it with --system",
+            version
         );
     }
     
-    let total_size = resp.content_length().unwrap_or(0);
+    println!("Verifying SDK {}...", version.cyan());
+    let manifest = Manifest::load(&dir)?;
+    let report = match &manifest {
+        Some(m) => m.verify(&dir)?,
+        None => {
+            println!(
+                "{} No manifest for {} (installed by an older mpf-dev); checking the layout only",
+                "Note:".yellow(),
+                version
+            );
+            manifest::VerifyReport {
+                missing_layout: manifest::missing_layout(&dir),
+                ..Default::default()
+            }
+        }
+    };
     
-    let pb = ProgressBar::new(total_size);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.green} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")?
-            .progress_chars("#>-"),
-    );
+    print_verify_section("Missing SDK entries", &report.missing_layout);
+    print_verify_section("Missing files", &report.missing);
+    print_verify_section("Modified files", &report.modified);
+    print_verify_section("Extra files", &report.extra);
     
-    // Download to temp file
-    let temp_ext = if is_tarball { "tar.gz.tmp" } else { "zip.tmp" };
-    let temp_path = dest.with_extension(temp_ext);
-    if let Some(parent) = temp_path.parent() {
-        fs::create_dir_all(parent)?;
+    if report.is_clean() && (manifest.is_some() || !repair) {
+        println!("{} SDK {} is intact", "✓".green(), version);
+        return Ok(());
+    }
+    if !repair {
+        bail!(
+            "SDK {} failed verification; run `mpf-dev sdk verify {} --repair` to fix it",
+            version,
+            version
+        );
     }
     
-    let mut file = File::create(&temp_path)?;
-    let mut downloaded: u64 = 0;
-    let mut stream = resp.bytes_stream();
-    
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        file.write_all(&chunk)?;
-        downloaded += chunk.len() as u64;
-        pb.set_position(downloaded);
+    if manifest.is_some() && !report.needs_download() {
+        for path in &report.extra {
+            fs::remove_file(dir.join(path))
+                .with_context(|| format!("Failed to remove {}", path))?;
+        }
+        println!("{} Removed {} extra file(s)", "✓".green(), report.extra.len());
+        return Ok(());
     }
     
-    pb.finish_with_message("Downloaded");
+    println!("Re-installing {} from the release...", version);
+    let asset = manifest.and_then(|m| m.asset);
+    reinstall_version(source, &version, AssetSelector::new(asset, None)).await?;
+    if let Some(root) = dir.parent() {
+        dedupe_after_install(root);
+    }
+    println!("{} SDK {} repaired", "✓".green(), version);
+    Ok(())
+}
+
+/// Header holding the SDK's version macros
+const VERSION_HEADER: &str = "include/mpf/version.h";
+
+/// `sdk info`: version macros of an installed SDK, the Qt version it was built
+/// against and its install record
+pub fn sdk_info(version: Option<String>) -> Result<()> {
+    let version = version
+        .map(|v| version::normalize(&v))
+        .or_else(config::current_version)
+        .context("No SDK version given and no current version set")?;
+    let dir = config::version_dir(&version)?;
+    if !dir.is_dir() {
+        bail!("SDK {} is not installed", version);
+    }
     
-    // Extract
-    println!("Extracting...");
-    fs::create_dir_all(dest)?;
+    println!("{} {}", "SDK".bold(), version.cyan());
     
-    if is_tarball {
-        // Extract tar.gz using tar command (more reliable on Unix)
-        let status = Command::new("tar")
-            .args(["-xzf", &temp_path.to_string_lossy(), "-C", &dest.to_string_lossy()])
-            .status()
-            .context("Failed to run tar command")?;
-        if !status.success() {
-            bail!("tar extraction failed");
-        }
+    let defines = fs::read_to_string(dir.join(VERSION_HEADER))
+        .map(|content| parse_defines(&content))
+        .unwrap_or_default();
+    if defines.is_empty() {
+        println!(
+            "  {} {} is missing or defines nothing",
+            "Note:".yellow(),
+            VERSION_HEADER
+        );
     } else {
-        // Extract zip
-        let file = File::open(&temp_path)?;
-        let mut archive = zip::ZipArchive::new(file)?;
-        archive.extract(dest)?;
+        println!("  {}", VERSION_HEADER.dimmed());
+        for (name, value) in &defines {
+            println!("    {:<28} {}", name, value);
+        }
     }
     
-    // Clean up temp file
-    fs::remove_file(&temp_path)?;
+    match qt_version(&dir, &defines) {
+        Some((qt, found_in)) => {
+            println!("  Qt:        {} {}", qt, format!("(from {})", found_in).dimmed())
+        }
+        None => println!(
+            "  Qt:        {}",
+            "unknown (no Qt version macro, CMake package or library found)".dimmed()
+        ),
+    }
     
-    println!("{} Extraction complete", "✓".green());
+    print_install_details(&dir, "  ");
     Ok(())
 }
 
-/// Versions command: list installed versions
-pub fn versions() -> Result<()> {
-    let versions = config::installed_versions();
-    let current = config::current_version();
+/// `#define NAME value` lines of a header, with quotes and trailing comments removed.
+/// Function-like macros and bare flags are skipped.
+fn parse_defines(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let rest = line.trim().strip_prefix('#')?.trim_start().strip_prefix("define")?;
+            let rest = rest.split("//").next()?.split("/*").next()?;
+            let (name, value) = rest.trim().split_once(char::is_whitespace)?;
+            if name.contains('(') {
+                return None;
+            }
+            Some((name.to_string(), value.trim().trim_matches('"').to_string()))
+        })
+        .collect()
+}
+
+/// Qt version the SDK was built against, and the file it was read from
+fn qt_version(dir: &Path, defines: &[(String, String)]) -> Option<(String, String)> {
+    // Recorded by the SDK itself (e.g. MPF_QT_VERSION)
+    if let Some((_, value)) = defines
+        .iter()
+        .find(|(name, _)| name.contains("QT") && name.contains("VERSION"))
+    {
+        return Some((value.clone(), VERSION_HEADER.to_string()));
+    }
     
-    if versions.is_empty() {
-        println!("No SDK versions installed.");
-        println!("Run {} to install.", "mpf-dev setup".cyan());
-        return Ok(());
+    // Qt's CMake package, when the SDK bundles Qt
+    for qt in ["Qt6", "Qt5"] {
+        let rel = format!("lib/cmake/{0}Core/{0}CoreConfigVersion.cmake", qt);
+        let Ok(content) = fs::read_to_string(dir.join(&rel)) else {
+            continue;
+        };
+        let version = content.lines().find_map(|line| {
+            let value = line.trim().strip_prefix("set(PACKAGE_VERSION")?;
+            Some(value.trim().trim_end_matches(')').trim_matches('"').to_string())
+        });
+        if let Some(version) = version {
+            return Some((version, rel));
+        }
     }
     
-    println!("{}", "Installed SDK versions:".bold());
-    for v in &versions {
-        if Some(v) == current.as_ref() {
-            println!("  {} {} {}", "*".green(), v.green(), "(current)".dimmed());
-        } else {
-            println!("    {}", v);
+    // Qt headers
+    for rel in [
+        "include/QtCore/qtcore-config.h",
+        "include/QtCore/qconfig.h",
+        "include/QtCore/qtversion.h",
+    ] {
+        let Ok(content) = fs::read_to_string(dir.join(rel)) else {
+            continue;
+        };
+        if let Some((_, version)) = parse_defines(&content)
+            .into_iter()
+            .find(|(name, _)| name == "QT_VERSION_STR")
+        {
+            return Some((version, rel.to_string()));
         }
     }
     
-    Ok(())
+    // Versioned library names (libQt6Core.so.6.5.3)
+    for entry in fs::read_dir(dir.join("lib")).ok()?.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let version = ["libQt6Core.so.", "libQt5Core.so."]
+            .iter()
+            .find_map(|prefix| name.strip_prefix(prefix))
+            .filter(|v| v.split('.').count() == 3);
+        if let Some(version) = version {
+            return Some((version.to_string(), format!("lib/{}", name)));
+        }
+    }
+    None
 }
 
-/// Use command: switch SDK version
-pub fn use_version(version: &str) -> Result<()> {
-    let version_normalized = if version.starts_with('v') {
-        version.to_string()
-    } else {
-        format!("v{}", version)
-    };
-    
-    let version_dir = config::version_dir(&version_normalized);
+/// `sdk dedupe`: replace files that are identical across installed versions with hard links
+pub fn sdk_dedupe() -> Result<()> {
+    let root = config::store_root(config::selected_store())?;
+    let versions = config::versions_in(&root);
+    if versions.len() < 2 {
+        println!("Nothing to deduplicate ({} version(s) installed).", versions.len());
+        return Ok(());
+    }
     
-    if !version_dir.exists() {
-        bail!(
-            "Version {} is not installed. Run `mpf-dev setup --version {}`",
-            version_normalized,
-            version
+    println!("Deduplicating {} SDK versions in {}...", versions.len(), root.display());
+    let report = dedupe::dedupe_store(&root)?;
+    if report.linked == 0 {
+        println!("{} No new duplicate files found", "✓".green());
+    } else {
+        println!(
+            "{} Hard-linked {} identical file(s), {} saved",
+            "✓".green(),
+            report.linked,
+            indicatif::HumanBytes(report.saved)
         );
     }
+    Ok(())
+}
+
+fn print_verify_section(title: &str, paths: &[String]) {
+    /// Entries listed per section before summarizing the rest
+    const MAX_LISTED: usize = 20;
     
-    config::set_current_version(&version_normalized)?;
-    
-    // Update dev.json
-    let mut dev_config = DevConfig::load().unwrap_or_default();
-    dev_config.sdk_version = Some(version_normalized.clone());
-    dev_config.save()?;
+    if paths.is_empty() {
+        return;
+    }
+    println!("{} ({}):", title.yellow(), paths.len());
+    for path in paths.iter().take(MAX_LISTED) {
+        println!("  {}", path);
+    }
+    if paths.len() > MAX_LISTED {
+        println!("  ... and {} more", paths.len() - MAX_LISTED);
+    }
+}
+
+/// Download and extract a version again, replacing the installed copy only on success
+async fn reinstall_version(source: &ReleaseSource, version: &str, selector: AssetSelector) -> Result<()> {
+    let dir = config::install_dir(version)?;
+    let old = dir.with_file_name(format!("{}{}-{}", REMOVING_PREFIX, version, std::process::id()));
+    fs::rename(&dir, &old)
+        .with_context(|| format!("Failed to move {} aside", dir.display()))?;
+    
+    match download_and_extract(source, version, &dir, &selector, false).await {
+        Ok(()) => {
+            // Symbols and sources are not part of the archive; carry them over
+            for companion in Companion::installed(&old) {
+                let _ = fs::rename(old.join(companion.dir_name()), dir.join(companion.dir_name()));
+            }
+            let _ = fs::remove_dir_all(&old);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::rename(&old, &dir);
+            Err(e)
+        }
+    }
+}
+
+/// `cache list`: show cached SDK archives
+pub fn cache_list() -> Result<()> {
+    let mut entries = cache::entries();
+    if entries.is_empty() {
+        println!("The download cache is empty ({})", config::cache_dir()?.display());
+        return Ok(());
+    }
+    entries.sort_by(|a, b| version::compare_versions(&b.version, &a.version));
     
+    println!("{}", "Cached SDK archives:".bold());
+    for entry in &entries {
+        let age = entry.last_used.elapsed().map(|d| d.as_secs() / 86400).unwrap_or(0);
+        println!(
+            "  {:<16} {:<32} {:>10}  {}",
+            entry.version,
+            entry.asset,
+            indicatif::HumanBytes(entry.size).to_string(),
+            format!("used {} day(s) ago", age).dimmed()
+        );
+    }
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    println!("Total: {} in {}", indicatif::HumanBytes(total), config::cache_dir()?.display());
+    Ok(())
+}
+
+/// `cache clean`: delete every cached archive
+pub fn cache_clean() -> Result<()> {
+    let entries = cache::entries();
+    let total: u64 = entries.iter().map(|e| e.size).sum();
+    for entry in &entries {
+        cache::remove(entry);
+    }
     println!(
-        "{} Now using SDK {}",
+        "{} Removed {} cached archive(s), freed {}",
         "✓".green(),
-        version_normalized
+        entries.len(),
+        indicatif::HumanBytes(total)
     );
+    Ok(())
+}
+
+/// `cache prune --max-size`: drop least recently used archives until the cache fits
+pub fn cache_prune(max_size: &str) -> Result<()> {
+    let limit = cache::parse_size(max_size)?;
+    let mut entries = cache::entries();
+    entries.sort_by_key(|e| e.last_used);
+    
+    let mut total: u64 = entries.iter().map(|e| e.size).sum();
+    let mut freed = 0;
+    let mut removed = 0;
+    for entry in &entries {
+        if total <= limit {
+            break;
+        }
+        cache::remove(entry);
+        println!("  Removed {} ({})", entry.asset, entry.version);
+        total -= entry.size;
+        freed += entry.size;
+        removed += 1;
+    }
     
+    println!(
+        "{} Removed {} cached archive(s), freed {}; cache is now {}",
+        "✓".green(),
+        removed,
+        indicatif::HumanBytes(freed),
+        indicatif::HumanBytes(total)
+    );
     Ok(())
 }
 
@@ -625,8 +2553,8 @@ pub fn unlink(component: &str) -> Result<()> {
 /// Status command: show current configuration
 pub fn status() -> Result<()> {
     let dev_config = DevConfig::load().unwrap_or_default();
-    let current = config::current_version();
-    let sdk_root = config::sdk_root();
+    let active = config::active_version()?;
+    let sdk_root = config::sdk_root()?;
     
     println!("{}", "MPF Development Environment Status".bold().cyan());
     println!();
@@ -634,8 +2562,23 @@ pub fn status() -> Result<()> {
     // SDK info
     println!("{}", "📦 SDK".bold());
     println!("  Root: {}", sdk_root.display());
-    if let Some(v) = &current {
-        println!("  Version: {}", v.green());
+    if let Some(system) = config::system_root().filter(|root| root.is_dir()) {
+        println!("  System store: {}", system.display());
+    }
+    if let Some((v, source)) = &active {
+        println!("  Version: {} {}", v.green(), format!("(from {})", source).dimmed());
+        if !config::version_dir(v)?.exists() {
+            println!(
+                "  {} not installed; run `mpf-dev setup --locked` or `mpf-dev setup --version {}`",
+                "✗".red(),
+                v
+            );
+        }
+        if !matches!(source, config::VersionSource::Global) {
+            if let Some(global) = config::current_version() {
+                println!("  Global: {}", global);
+            }
+        }
     } else {
         println!("  Version: {}", "not set".red());
     }
@@ -713,7 +2656,7 @@ pub fn status() -> Result<()> {
     
     // Config file location
     println!("{}", "📝 Config".bold());
-    println!("  {}", config::dev_config_path().display());
+    println!("  {}", config::dev_config_path()?.display());
     
     Ok(())
 }
@@ -723,6 +2666,9 @@ pub fn env_vars() -> Result<()> {
     let (sdk_root, lib_path, qml_path, plugin_path, mpf_plugin_path, _host_path) = build_env_paths()?;
     
     println!("{}", "# MPF Development Environment".bold().cyan());
+    if let Some((version, source)) = config::active_version()? {
+        println!("{}", format!("# SDK {} (from {})", version, source).dimmed());
+    }
     println!("{}", "# Add these to your shell or IDE:".dimmed());
     println!();
     
@@ -830,12 +2776,7 @@ fn detect_qt_path() -> Option<String> {
 }
 
 /// Run command: execute mpf-host with development overrides
-pub fn run(debug: bool, args: Vec<String>) -> Result<()> {
-    let current = config::current_link();
-    if !current.exists() {
-        bail!("No SDK version set. Run `mpf-dev setup` first.");
-    }
-    
+pub fn run(debug: bool, debugger: Option<String>, args: Vec<String>) -> Result<()> {
     let (sdk_root, lib_path, qml_path, plugin_path, mpf_plugin_path, host_path) = build_env_paths()?;
     
     if !host_path.exists() {
@@ -857,16 +2798,188 @@ pub fn run(debug: bool, args: Vec<String>) -> Result<()> {
         println!();
     }
     
-    let mut cmd = Command::new(&host_path);
+    let mut cmd = match &debugger {
+        Some(program) => {
+            let kind = Debugger::detect(program).with_context(|| {
+                format!("Unsupported debugger '{}'; use gdb or lldb", program)
+            })?;
+            if !Path::new(&sdk_root).join(Companion::Symbols.dir_name()).is_dir() {
+                let version = config::active_version()?.map(|(v, _)| v).unwrap_or_default();
+                println!(
+                    "{} SDK {} has no debug symbols installed; add them with `mpf-dev setup --version {} --with-symbols`",
+                    "Note:".yellow(),
+                    version,
+                    version
+                );
+            }
+            let mut cmd = Command::new(program);
+            cmd.args(kind.sdk_args(Path::new(&sdk_root)));
+            cmd.arg(kind.program_separator()).arg(&host_path);
+            cmd
+        }
+        None => Command::new(&host_path),
+    };
     cmd.args(&args);
+    apply_sdk_env(&mut cmd, &sdk_root, &lib_path, &qml_path, &plugin_path, &mpf_plugin_path);
+    
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to run {}", debugger.as_deref().unwrap_or("mpf-host")))?;
+    
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Exec command: run any program (cmake, ctest, an IDE, a shell) with the SDK environment
+pub fn exec(command: Vec<String>) -> Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("No command given. Usage: mpf-dev exec <command> [args...]");
+    };
+    
+    let (sdk_root, lib_path, qml_path, plugin_path, mpf_plugin_path, _host_path) = build_env_paths()?;
+    
+    let mut cmd = Command::new(program);
+    if let Some(debugger) = Debugger::detect(program) {
+        cmd.args(debugger.sdk_args(Path::new(&sdk_root)));
+    }
+    cmd.args(args);
+    apply_sdk_env(&mut cmd, &sdk_root, &lib_path, &qml_path, &plugin_path, &mpf_plugin_path);
+    
+    // Let CMake find the SDK package
+    let sep = if cfg!(windows) { ";" } else { ":" };
+    let prefix_path = match env::var("CMAKE_PREFIX_PATH") {
+        Ok(existing) if !existing.is_empty() => format!("{}{}{}", sdk_root, sep, existing),
+        _ => sdk_root.clone(),
+    };
+    cmd.env("CMAKE_PREFIX_PATH", prefix_path);
+    
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// File at the root of the sources archive naming the directory the SDK was built in
+const SOURCE_PREFIX_FILE: &str = "source-prefix.txt";
+
+/// Debuggers that get pointed at the SDK's symbols and sources
+#[derive(Debug, Clone, Copy)]
+enum Debugger {
+    Gdb,
+    Lldb,
+}
+
+impl Debugger {
+    /// Recognise gdb and lldb by executable name (`gdb-multiarch`, `lldb-18`, ...)
+    fn detect(program: &str) -> Option<Self> {
+        let stem = Path::new(program).file_stem()?.to_string_lossy().to_lowercase();
+        if stem.starts_with("gdb") {
+            Some(Debugger::Gdb)
+        } else if stem.starts_with("lldb") {
+            Some(Debugger::Lldb)
+        } else {
+            None
+        }
+    }
+    
+    /// Argument after which the debugger takes the program to debug and its arguments
+    fn program_separator(self) -> &'static str {
+        match self {
+            Debugger::Gdb => "--args",
+            Debugger::Lldb => "--",
+        }
+    }
     
+    /// Startup commands for the debug symbols and sources installed in `sdk_root`
+    fn sdk_args(self, sdk_root: &Path) -> Vec<String> {
+        let symbols = sdk_root.join(Companion::Symbols.dir_name());
+        let sources = sdk_root.join(Companion::Sources.dir_name());
+        let prefix = fs::read_to_string(sources.join(SOURCE_PREFIX_FILE))
+            .ok()
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+        
+        let mut commands = Vec::new();
+        match self {
+            Debugger::Gdb => {
+                if symbols.is_dir() {
+                    // Replacing the directory list drops gdb's default, so keep it
+                    let mut dirs = vec![symbols.clone()];
+                    if cfg!(unix) {
+                        dirs.push(PathBuf::from("/usr/lib/debug"));
+                    }
+                    if let Ok(dirs) = env::join_paths(dirs) {
+                        commands.push(format!(
+                            "set debug-file-directory {}",
+                            dirs.to_string_lossy()
+                        ));
+                    }
+                }
+                if sources.is_dir() {
+                    if let Some(prefix) = &prefix {
+                        commands.push(format!(
+                            "set substitute-path \"{}\" \"{}\"",
+                            prefix,
+                            sources.display()
+                        ));
+                    }
+                    commands.push(format!("directory {}", sources.display()));
+                }
+            }
+            Debugger::Lldb => {
+                if symbols.is_dir() {
+                    commands.push(format!(
+                        "settings append target.debug-file-search-paths \"{}\"",
+                        symbols.display()
+                    ));
+                }
+                // lldb has no search path for sources, only prefix mappings
+                if let Some(prefix) = prefix.as_ref().filter(|_| sources.is_dir()) {
+                    commands.push(format!(
+                        "settings set target.source-map \"{}\" \"{}\"",
+                        prefix,
+                        sources.display()
+                    ));
+                }
+            }
+        }
+        
+        // Both run these before loading the program
+        let flag = match self {
+            Debugger::Gdb => "-iex",
+            Debugger::Lldb => "-O",
+        };
+        commands
+            .into_iter()
+            .flat_map(|command| [flag.to_string(), command])
+            .collect()
+    }
+}
+
+/// Set the variables mpf-host and the SDK libraries need on a child process
+fn apply_sdk_env(
+    cmd: &mut Command,
+    sdk_root: &str,
+    lib_path: &str,
+    qml_path: &str,
+    plugin_path: &str,
+    mpf_plugin_path: &str,
+) {
     // MPF_SDK_ROOT tells mpf-host where the SDK is installed
     // This is the primary way mpf-host discovers its paths
-    cmd.env("MPF_SDK_ROOT", &sdk_root);
+    cmd.env("MPF_SDK_ROOT", sdk_root);
+    
+    // Nested mpf-dev calls resolve to the same root and version
+    if let Ok(root) = config::sdk_root() {
+        cmd.env("MPF_SDK_HOME", root);
+    }
+    if let Some((version, _)) = config::active_version().ok().flatten() {
+        cmd.env("MPF_SDK_VERSION", version);
+    }
     
     #[cfg(unix)]
     {
-        cmd.env("LD_LIBRARY_PATH", &lib_path);
+        cmd.env("LD_LIBRARY_PATH", lib_path);
     }
     
     #[cfg(windows)]
@@ -875,18 +2988,14 @@ pub fn run(debug: bool, args: Vec<String>) -> Result<()> {
         cmd.env("PATH", format!("{};{}", lib_path, current_path));
     }
     
-    cmd.env("QML_IMPORT_PATH", &qml_path);
-    cmd.env("QT_PLUGIN_PATH", &plugin_path);
+    cmd.env("QML_IMPORT_PATH", qml_path);
+    cmd.env("QT_PLUGIN_PATH", plugin_path);
     
     // Set MPF_PLUGIN_PATH for mpf-host to discover linked plugins
     // This allows linked source plugins to override SDK binary plugins
     if !mpf_plugin_path.is_empty() {
-        cmd.env("MPF_PLUGIN_PATH", &mpf_plugin_path);
+        cmd.env("MPF_PLUGIN_PATH", mpf_plugin_path);
     }
-    
-    let status = cmd.status()?;
-    
-    std::process::exit(status.code().unwrap_or(1));
 }
 
 // =============================================================================
@@ -1399,10 +3508,22 @@ pub fn workspace_status() -> Result<()> {
 /// Returns: (sdk_root, lib_path, qml_path, qt_plugin_path, mpf_plugin_path, host_path)
 fn build_env_paths() -> Result<(String, String, String, String, String, PathBuf)> {
     let dev_config = DevConfig::load().unwrap_or_default();
-    let sdk = config::current_link();
+    let Some((version, source)) = config::active_version()? else {
+        bail!("No SDK version set. Run 'mpf-dev setup' first.");
+    };
+    let sdk = config::version_dir(&version)?;
     
     if !sdk.exists() {
-        bail!("No SDK version set. Run 'mpf-dev setup' first.");
+        let hint = match source {
+            config::VersionSource::PinFile(_) => "mpf-dev setup --locked".to_string(),
+            _ => format!("mpf-dev setup --version {}", version),
+        };
+        bail!(
+            "SDK {} (from {}) is not installed. Run '{}'.",
+            version,
+            source,
+            hint
+        );
     }
     
     // SDK root path (used by mpf-host to find default paths)
@@ -1471,3 +3592,87 @@ fn build_env_paths() -> Result<(String, String, String, String, String, PathBuf)
         host_path,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const HASH_A: &str = "a3f1c5d2e4b6a8c0e2f4a6b8c0d2e4f6a8b0c2d4e6f8a0b2c4d6e8f0a2b4c6d8";
+    const HASH_B: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+    
+    #[test]
+    fn checksum_list_finds_the_asset() {
+        let list = format!(
+            "{}  mpf-windows-x64.zip\n{}  mpf-linux-x64.tar.gz\n",
+            HASH_B, HASH_A
+        );
+        assert_eq!(parse_checksum_list(&list, "mpf-linux-x64.tar.gz").as_deref(), Some(HASH_A));
+        assert_eq!(parse_checksum_list(&list, "mpf-windows-x64.zip").as_deref(), Some(HASH_B));
+        assert_eq!(parse_checksum_list(&list, "mpf-macos-arm64.tar.gz"), None);
+    }
+    
+    #[test]
+    fn checksum_list_accepts_binary_mode_and_crlf() {
+        let list = format!("{} *mpf-linux-x64.tar.gz\r\n", HASH_A);
+        assert_eq!(parse_checksum_list(&list, "mpf-linux-x64.tar.gz").as_deref(), Some(HASH_A));
+    }
+    
+    #[test]
+    fn checksum_list_skips_malformed_lines() {
+        let asset = "mpf-linux-x64.tar.gz";
+        let malformed = [
+            String::new(),
+            "\n\n".to_string(),
+            asset.to_string(),
+            format!("{}\n", HASH_A),
+            format!("not-a-hash  {}", asset),
+            format!("{}  {}", &HASH_A[..63], asset),
+            format!("{}0  {}", HASH_A, asset),
+            format!("{}  {}", HASH_A.replace('a', "g"), asset),
+            format!("{}  {} extra", HASH_A, asset),
+            format!("{}  {}.sig", HASH_A, asset),
+            format!("{}  sub/{}", HASH_A, asset),
+        ];
+        for list in &malformed {
+            assert_eq!(parse_checksum_list(list, asset), None, "{:?} accepted", list);
+        }
+        
+        // A malformed line does not hide a valid one after it
+        let list = format!("garbage  {}\n{}  {}\n", asset, HASH_A, asset);
+        assert_eq!(parse_checksum_list(&list, asset).as_deref(), Some(HASH_A));
+    }
+    
+    #[test]
+    fn partial_downloads_match_the_exact_version_and_asset() {
+        let asset = "mpf-linux-x64.tar.gz";
+        let beta = partial_download_name("v1.2.3-beta.1", asset);
+        assert!(is_partial_download_of(&beta, "v1.2.3-beta.1", Some(asset)));
+        assert!(!is_partial_download_of(&beta, "v1.2.3", Some(asset)));
+        
+        let release = partial_download_name("v1.2.3", asset);
+        assert!(is_partial_download_of(&release, "v1.2.3", Some(asset)));
+        assert!(!is_partial_download_of(&release, "v1.2.3", Some("mpf-linux-arm64.tar.gz")));
+        assert!(!is_partial_download_of(&release, "v1.2.3", None));
+        assert!(!is_partial_download_of(&release, "v1.2", Some(asset)));
+        
+        // Older naming, without the asset
+        assert!(is_partial_download_of("v1.2.3.tar.gz.tmp", "v1.2.3", None));
+        assert!(is_partial_download_of("v1.2.3.zip.tmp", "v1.2.3", Some(asset)));
+        assert!(!is_partial_download_of("v1.2.3-beta.1.tar.gz.tmp", "v1.2.3", None));
+    }
+    
+    #[test]
+    fn gdb_keeps_the_system_debug_directory() {
+        let sdk_root = env::temp_dir().join(format!("mpf-dev-gdb-{}", std::process::id()));
+        let symbols = sdk_root.join(Companion::Symbols.dir_name());
+        fs::create_dir_all(&symbols).unwrap();
+        
+        let expected = if cfg!(unix) {
+            format!("set debug-file-directory {}:/usr/lib/debug", symbols.display())
+        } else {
+            format!("set debug-file-directory {}", symbols.display())
+        };
+        assert_eq!(Debugger::Gdb.sdk_args(&sdk_root), vec!["-iex".to_string(), expected]);
+        let _ = fs::remove_dir_all(&sdk_root);
+    }
+}
@@ -1,43 +1,152 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
-/// SDK root directory (~/.mpf-sdk)
-pub fn sdk_root() -> PathBuf {
-    dirs::home_dir()
-        .expect("Could not find home directory")
-        .join(".mpf-sdk")
+use crate::version;
+
+static SDK_ROOT_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `path` as the SDK root for this run (`--sdk-root`)
+pub fn set_sdk_root(path: PathBuf) {
+    let _ = SDK_ROOT_OVERRIDE.set(path);
 }
 
-/// Path to dev.json configuration
-pub fn dev_config_path() -> PathBuf {
-    sdk_root().join("dev.json")
+/// SDK root directory: `--sdk-root`, then MPF_SDK_HOME, then ~/.mpf-sdk
+pub fn sdk_root() -> Result<PathBuf> {
+    let root = if let Some(root) = SDK_ROOT_OVERRIDE.get() {
+        root.clone()
+    } else if let Some(root) = std::env::var_os("MPF_SDK_HOME").filter(|r| !r.is_empty()) {
+        PathBuf::from(root)
+    } else {
+        dirs::home_dir()
+            .context("Could not find the home directory; set MPF_SDK_HOME or pass --sdk-root")?
+            .join(".mpf-sdk")
+    };
+    // Version symlinks and child processes need it independent of the cwd
+    std::path::absolute(&root)
+        .with_context(|| format!("Invalid SDK root {}", root.display()))
 }
 
-/// Path to current version pointer file (stores version name)
-pub fn current_pointer_path() -> PathBuf {
-    sdk_root().join("current.txt")
+static SYSTEM_STORE_SELECTED: OnceLock<bool> = OnceLock::new();
+
+/// Make installs and removals in this run target the system store (`--system`)
+pub fn select_system_store() {
+    let _ = SYSTEM_STORE_SELECTED.set(true);
+}
+
+pub fn system_store_selected() -> bool {
+    SYSTEM_STORE_SELECTED.get().copied().unwrap_or(false)
+}
+
+/// Shared, admin-installed SDK store searched after the user's own:
+/// MPF_SDK_SYSTEM_HOME (empty disables it), else /opt/mpf-sdk (%ProgramData%\mpf-sdk on Windows)
+pub fn system_root() -> Option<PathBuf> {
+    if let Some(root) = std::env::var_os("MPF_SDK_SYSTEM_HOME") {
+        return (!root.is_empty()).then(|| PathBuf::from(root));
+    }
+    if cfg!(windows) {
+        std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join("mpf-sdk"))
+    } else {
+        Some(PathBuf::from("/opt/mpf-sdk"))
+    }
+}
+
+/// Where an installed SDK version lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    /// The user's SDK root (writable)
+    User,
+    
+    /// The shared system store (read-only unless `--system`)
+    System,
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Store::User => write!(f, "user"),
+            Store::System => write!(f, "system"),
+        }
+    }
+}
+
+/// Root directory of a store
+pub fn store_root(store: Store) -> Result<PathBuf> {
+    match store {
+        Store::User => sdk_root(),
+        Store::System => system_root()
+            .context("No system SDK store is configured; set MPF_SDK_SYSTEM_HOME"),
+    }
 }
 
-/// Get the current SDK directory path
-/// On Unix: follows symlink if exists, falls back to pointer file
-/// On Windows: always uses pointer file
-pub fn current_link() -> PathBuf {
-    if let Some(version) = current_version() {
-        version_dir(&version)
+/// Store that `setup`, `remove` and `gc` write to
+pub fn selected_store() -> Store {
+    if system_store_selected() {
+        Store::System
     } else {
-        // Fallback - this path won't exist but callers check existence
-        sdk_root().join("current")
+        Store::User
     }
 }
 
-/// Path to a specific version directory
-pub fn version_dir(version: &str) -> PathBuf {
-    sdk_root().join(version)
+/// Directory `setup` installs a version into
+pub fn install_dir(version: &str) -> Result<PathBuf> {
+    version::validate(version)?;
+    Ok(store_root(selected_store())?.join(version))
+}
+
+/// Stores holding a version, the user's first
+pub fn version_stores(version: &str) -> Vec<Store> {
+    [Store::User, Store::System]
+        .into_iter()
+        .filter(|store| store_root(*store).is_ok_and(|root| root.join(version).is_dir()))
+        .collect()
+}
+
+/// Path to dev.json configuration
+pub fn dev_config_path() -> Result<PathBuf> {
+    Ok(sdk_root()?.join("dev.json"))
+}
+
+/// Path to current version pointer file (stores version name)
+pub fn current_pointer_path() -> Result<PathBuf> {
+    Ok(sdk_root()?.join("current.txt"))
+}
+
+/// Path to the cached remote release index
+pub fn release_index_path() -> Result<PathBuf> {
+    Ok(sdk_root()?.join("releases.json"))
+}
+
+/// Path to the cached result of the last update check
+pub fn update_check_path() -> Result<PathBuf> {
+    Ok(sdk_root()?.join("update-check.json"))
 }
 
+/// Directory holding downloaded SDK archives
+pub fn cache_dir() -> Result<PathBuf> {
+    Ok(sdk_root()?.join("cache"))
+}
+
+/// Path to a specific version directory: the user's copy, else the system store's.
+/// For versions installed nowhere this is where `setup` would put them.
+pub fn version_dir(version: &str) -> Result<PathBuf> {
+    version::validate(version)?;
+    let user = sdk_root()?.join(version);
+    if !user.is_dir() {
+        if let Some(system) = system_root().map(|root| root.join(version)).filter(|dir| dir.is_dir()) {
+            return Ok(system);
+        }
+    }
+    Ok(user)
+}
+
+/// Per-project pin file naming the SDK version a checkout expects
+pub const PIN_FILE: &str = ".mpf-version";
+
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct DevConfig {
     #[serde(default)]
@@ -45,6 +154,83 @@ pub struct DevConfig {
     
     #[serde(default)]
     pub components: HashMap<String, ComponentConfig>,
+    
+    /// Project pin files seen by mpf-dev; versions they name are kept by `gc`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pin_files: Vec<String>,
+    
+    /// Release source override (mirrors, GitHub Enterprise, local directories)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release: Option<ReleaseConfig>,
+    
+    /// Proxy, CA and timeout settings for downloads
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkConfig>,
+    
+    /// Channel for `update` and the new-SDK notice
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update: Option<UpdateConfig>,
+}
+
+/// `release` section of dev.json; unset fields fall back to the defaults
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReleaseConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    
+    /// GitHub token; GITHUB_TOKEN and GH_TOKEN take precedence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    
+    /// minisign public key trusted for SDK signatures, in addition to the built-in one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    
+    /// Refuse SDK archives without a valid signature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_signatures: Option<bool>,
+}
+
+/// `network` section of dev.json
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NetworkConfig {
+    /// Proxy URL for all requests; overrides HTTPS_PROXY/HTTP_PROXY/ALL_PROXY
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    
+    /// Comma-separated hosts that bypass `proxy`; defaults to NO_PROXY
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    
+    /// Extra PEM bundles of trusted root certificates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_certs: Vec<String>,
+    
+    /// Connect timeout in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    
+    /// Read timeout in seconds (how long a transfer may stall)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_timeout: Option<u64>,
+}
+
+/// `update` section of dev.json
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct UpdateConfig {
+    /// Release channel followed by `update` (default: stable)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<version::Channel>,
+    
+    /// Show the daily "new SDK available" notice on `status` and `run` (default: true)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notify: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -77,7 +263,7 @@ pub enum ComponentMode {
 
 impl DevConfig {
     pub fn load() -> Result<Self> {
-        let path = dev_config_path();
+        let path = dev_config_path()?;
         if path.exists() {
             let content = fs::read_to_string(&path)
                 .with_context(|| format!("Failed to read {}", path.display()))?;
@@ -89,7 +275,7 @@ impl DevConfig {
     }
     
     pub fn save(&self) -> Result<()> {
-        let path = dev_config_path();
+        let path = dev_config_path()?;
         
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
@@ -97,25 +283,35 @@ impl DevConfig {
         }
         
         let content = serde_json::to_string_pretty(self)?;
-        fs::write(&path, content)
-            .with_context(|| format!("Failed to write {}", path.display()))?;
-        Ok(())
+        write_atomic(&path, content)
+    }
+}
+
+/// Write a file through a temporary file in the same directory and a rename,
+/// so another process never reads it half-written
+pub fn write_atomic(path: &std::path::Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    let result = fs::write(&temp, contents).and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
     }
+    result.with_context(|| format!("Failed to write {}", path.display()))
 }
 
 /// Get the current SDK version
 pub fn current_version() -> Option<String> {
-    let pointer = current_pointer_path();
+    let pointer = current_pointer_path().ok()?;
     if pointer.exists() {
         fs::read_to_string(&pointer)
             .ok()
             .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
+            .filter(|s| version::validate(s).is_ok())
     } else {
         // Fallback: check for legacy symlink on Unix
         #[cfg(unix)]
         {
-            let legacy = sdk_root().join("current");
+            let legacy = sdk_root().ok()?.join("current");
             if legacy.is_symlink() {
                 return fs::read_link(&legacy)
                     .ok()
@@ -128,34 +324,137 @@ pub fn current_version() -> Option<String> {
 
 /// Set the current SDK version
 pub fn set_current_version(version: &str) -> Result<()> {
-    let root = sdk_root();
+    let root = sdk_root()?;
     fs::create_dir_all(&root)?;
     
-    let pointer = current_pointer_path();
-    fs::write(&pointer, version)?;
+    let pointer = current_pointer_path()?;
+    write_atomic(&pointer, version)?;
     
-    // On Unix, also create/update symlink for compatibility
+    // On Unix, also create/update symlink for compatibility; it is replaced
+    // through a rename so it never goes missing
     #[cfg(unix)]
     {
         let link = root.join("current");
-        if link.exists() || link.is_symlink() {
-            let _ = fs::remove_file(&link);
+        let temp = root.join(format!(".current.{}.tmp", std::process::id()));
+        let _ = fs::remove_file(&temp);
+        let target = version_dir(version)?;
+        if std::os::unix::fs::symlink(&target, &temp).is_ok() && fs::rename(&temp, &link).is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Clear the current SDK version (pointer file and Unix symlink)
+pub fn clear_current_version() -> Result<()> {
+    let pointer = current_pointer_path()?;
+    if pointer.exists() {
+        fs::remove_file(&pointer)?;
+    }
+    
+    #[cfg(unix)]
+    {
+        let link = sdk_root()?.join("current");
+        if link.is_symlink() {
+            fs::remove_file(&link)?;
         }
-        let target = version_dir(version);
-        let _ = std::os::unix::fs::symlink(&target, &link);
     }
     
     Ok(())
 }
 
-/// List all installed SDK versions
+/// Pin files that apply to the current directory (walking up to the filesystem root)
+pub fn pin_files_from_cwd() -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let Ok(mut dir) = std::env::current_dir() else {
+        return found;
+    };
+    loop {
+        let candidate = dir.join(PIN_FILE);
+        if candidate.is_file() {
+            found.push(candidate);
+        }
+        if !dir.pop() {
+            return found;
+        }
+    }
+}
+
+/// Read the version named in a pin file. Pin files come with checkouts, so a
+/// version that is not a plain tag is an error rather than a path.
+pub fn read_pin_file(path: &std::path::Path) -> Result<Option<String>> {
+    let Some(version) = fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+    else {
+        return Ok(None);
+    };
+    version::validate(&version::normalize(&version))
+        .with_context(|| format!("Invalid pin file {}", path.display()))?;
+    Ok(Some(version))
+}
+
+/// Where the SDK version in effect was chosen
+#[derive(Debug, Clone)]
+pub enum VersionSource {
+    /// The MPF_SDK_VERSION environment variable
+    Env,
+    
+    /// The nearest `.mpf-version` above the current directory
+    PinFile(PathBuf),
+    
+    /// The global pointer (current.txt)
+    Global,
+}
+
+impl fmt::Display for VersionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionSource::Env => write!(f, "MPF_SDK_VERSION"),
+            VersionSource::PinFile(path) => write!(f, "{} at {}", PIN_FILE, path.display()),
+            VersionSource::Global => write!(f, "current.txt"),
+        }
+    }
+}
+
+/// SDK version in effect for the current directory:
+/// MPF_SDK_VERSION, then the nearest `.mpf-version`, then current.txt
+pub fn active_version() -> Result<Option<(String, VersionSource)>> {
+    if let Some(v) = std::env::var("MPF_SDK_VERSION").ok().filter(|v| !v.trim().is_empty()) {
+        let v = version::normalize(v.trim());
+        version::validate(&v).context("Invalid MPF_SDK_VERSION")?;
+        return Ok(Some((v, VersionSource::Env)));
+    }
+    if let Some(pin) = pin_files_from_cwd().into_iter().next() {
+        if let Some(v) = read_pin_file(&pin)? {
+            return Ok(Some((version::normalize(&v), VersionSource::PinFile(pin))));
+        }
+    }
+    Ok(current_version().map(|v| (v, VersionSource::Global)))
+}
+
+/// List all installed SDK versions in both stores, oldest first (semantic version order)
 pub fn installed_versions() -> Vec<String> {
-    let root = sdk_root();
+    let mut versions: Vec<String> = [Store::User, Store::System]
+        .into_iter()
+        .filter_map(|store| store_root(store).ok())
+        .flat_map(|root| versions_in(&root))
+        .collect();
+    
+    versions.sort_by(|a, b| version::compare_versions(a, b));
+    versions.dedup();
+    versions
+}
+
+/// SDK versions installed directly under `root`, oldest first
+pub fn versions_in(root: &std::path::Path) -> Vec<String> {
     if !root.exists() {
         return vec![];
     }
     
-    fs::read_dir(&root)
+    let mut versions: Vec<String> = fs::read_dir(root)
         .map(|entries| {
             entries
                 .filter_map(|e| e.ok())
@@ -168,7 +467,10 @@ pub fn installed_versions() -> Vec<String> {
                 .map(|e| e.file_name().to_string_lossy().to_string())
                 .collect()
         })
-        .unwrap_or_default()
+        .unwrap_or_default();
+    
+    versions.sort_by(|a, b| version::compare_versions(a, b));
+    versions
 }
 
 /// Known MPF components
@@ -0,0 +1,229 @@
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::manifest::{self, Manifest};
+
+/// Outcome of a deduplication pass
+#[derive(Debug, Default)]
+pub struct DedupeReport {
+    /// Files replaced with a hard link
+    pub linked: usize,
+    
+    /// Bytes freed on disk
+    pub saved: u64,
+}
+
+/// Replace files that are identical across the versions installed under `root`
+/// with hard links to a single copy.
+///
+/// Candidates are found through the manifests (hash, size and permissions) and
+/// compared byte for byte before linking, so a stale manifest never links
+/// different files. Deleting a version later only drops its links; the other
+/// versions keep the data.
+pub fn dedupe_store(root: &Path) -> Result<DedupeReport> {
+    let mut manifests = Vec::new();
+    for version in config::versions_in(root) {
+        let dir = root.join(&version);
+        // Versions installed before manifests existed are hashed on the spot
+        let manifest = match Manifest::load(&dir)? {
+            Some(manifest) => manifest,
+            None => Manifest::build(&dir, &version, None)?,
+        };
+        manifests.push((dir, manifest));
+    }
+    
+    let total = manifests.iter().map(|(_, m)| m.files.len() as u64).sum();
+    let pb = ProgressBar::new(total);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}")?
+            .progress_chars("#>-"),
+    );
+    
+    let mut report = DedupeReport::default();
+    let mut first_copies: HashMap<(&str, u64, Option<u32>), PathBuf> = HashMap::new();
+    for (dir, manifest) in &manifests {
+        for entry in &manifest.files {
+            pb.inc(1);
+            // Symlinks and empty files take no space worth sharing
+            if entry.link.is_some() || entry.size == 0 {
+                continue;
+            }
+            let path = dir.join(&entry.path);
+            let key = (entry.sha256.as_str(), entry.size, entry.mode);
+            let Some(original) = first_copies.get(&key) else {
+                first_copies.insert(key, path);
+                continue;
+            };
+            pb.set_message(entry.path.clone());
+            if let Some(saved) = link_if_identical(original, &path)? {
+                report.linked += 1;
+                report.saved += saved;
+            }
+        }
+    }
+    pb.finish_and_clear();
+    
+    Ok(report)
+}
+
+/// Replace `path` with a hard link to `original` if both have the same contents
+/// and permissions, returning the bytes freed
+fn link_if_identical(original: &Path, path: &Path) -> Result<Option<u64>> {
+    let (Ok(a), Ok(b)) = (fs::symlink_metadata(original), fs::symlink_metadata(path)) else {
+        return Ok(None);
+    };
+    if !a.is_file() || !b.is_file() || a.len() != b.len() || a.permissions() != b.permissions() {
+        return Ok(None);
+    }
+    // Without a way to tell files apart, linking could not be accounted for
+    let (Some(id_a), Some(id_b)) = (file_id(original), file_id(path)) else {
+        return Ok(None);
+    };
+    if id_a.same_file(&id_b) || !contents_equal(original, path)? {
+        return Ok(None);
+    }
+    
+    // Only the last link to the data frees it
+    let freed = if id_b.links == 1 { b.len() } else { 0 };
+    
+    // Link under a temporary name and rename over the file, so it never goes missing
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.mpf-link", name));
+    let _ = fs::remove_file(&temp);
+    fs::hard_link(original, &temp)
+        .with_context(|| format!("Failed to hard-link {}", path.display()))?;
+    if let Err(e) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(e).with_context(|| format!("Failed to replace {}", path.display()));
+    }
+    Ok(Some(freed))
+}
+
+fn contents_equal(a: &Path, b: &Path) -> Result<bool> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .with_context(|| format!("Failed to open {}", path.display()))
+    };
+    let (mut a, mut b) = (open(a)?, open(b)?);
+    let mut buf_a = [0u8; 64 * 1024];
+    let mut buf_b = [0u8; 64 * 1024];
+    loop {
+        let n = a.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(b.read(&mut buf_b)? == 0);
+        }
+        b.read_exact(&mut buf_b[..n])?;
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Bytes deleting `dir` would free: files still hard-linked from outside it do not count
+pub fn reclaimable_size(dir: &Path) -> u64 {
+    unshared_size(dir, false)
+}
+
+/// Like `reclaimable_size`, over the SDK files only: mpf-dev's own files at the top
+/// of the directory (manifest, install record, symbols, sources) are left out, as
+/// they are from the recorded install size
+pub fn sdk_reclaimable_size(dir: &Path) -> u64 {
+    unshared_size(dir, true)
+}
+
+fn unshared_size(dir: &Path, sdk_only: bool) -> u64 {
+    fn walk(path: &Path, out: &mut Vec<(PathBuf, Metadata)>) {
+        let Ok(meta) = fs::symlink_metadata(path) else {
+            return;
+        };
+        if !meta.is_dir() {
+            out.push((path.to_path_buf(), meta));
+            return;
+        }
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                walk(&entry.path(), out);
+            }
+        }
+    }
+    
+    let mut files = Vec::new();
+    match fs::read_dir(dir) {
+        Ok(entries) if sdk_only => {
+            for entry in entries.filter_map(|e| e.ok()) {
+                if !entry.file_name().to_string_lossy().starts_with(manifest::INTERNAL_PREFIX) {
+                    walk(&entry.path(), &mut files);
+                }
+            }
+        }
+        _ => walk(dir, &mut files),
+    }
+    
+    // (links in total, links inside `dir`, size) per file
+    let mut inodes: HashMap<(u64, u64), (u64, u64, u64)> = HashMap::new();
+    let mut unshared = 0;
+    for (path, meta) in &files {
+        // Symlinks are never hard-linked by dedupe; files of unknown identity count in full
+        let id = if meta.is_file() { file_id(path) } else { None };
+        let Some(id) = id else {
+            unshared += meta.len();
+            continue;
+        };
+        let entry = inodes
+            .entry((id.volume, id.index))
+            .or_insert((id.links, 0, meta.len()));
+        entry.1 += 1;
+    }
+    unshared
+        + inodes
+            .values()
+            .filter(|(links, inside, _)| inside >= links)
+            .map(|(_, _, size)| size)
+            .sum::<u64>()
+}
+
+/// Identity of a file on disk and how many hard links it has
+struct FileId {
+    volume: u64,
+    index: u64,
+    links: u64,
+}
+
+impl FileId {
+    fn same_file(&self, other: &FileId) -> bool {
+        self.volume == other.volume && self.index == other.index
+    }
+}
+
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = fs::symlink_metadata(path).ok()?;
+    Some(FileId {
+        volume: meta.dev(),
+        index: meta.ino(),
+        links: meta.nlink(),
+    })
+}
+
+#[cfg(windows)]
+fn file_id(path: &Path) -> Option<FileId> {
+    let info = winapi_util::file::information(File::open(path).ok()?).ok()?;
+    Some(FileId {
+        volume: info.volume_serial_number(),
+        index: info.file_index(),
+        links: info.number_of_links(),
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn file_id(_path: &Path) -> Option<FileId> {
+    None
+}
@@ -0,0 +1,367 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::asset;
+use crate::config::{self, Store};
+use crate::manifest::{self, Manifest};
+
+/// File manifest published next to an SDK asset, in the format of `.mpf-manifest.json`
+pub fn manifest_name(asset: &str) -> String {
+    format!("{}.manifest.json", asset)
+}
+
+/// Archive holding the files of `asset` that differ from version `base`,
+/// e.g. `mpf-linux-x64-delta-v1.2.0.tar.gz`
+pub fn delta_name(asset: &str, base: &str) -> String {
+    asset::companion_name(asset, &format!("delta-{}", base))
+}
+
+/// Which files of a target version are already on disk and which have to be fetched
+#[derive(Debug, Default)]
+pub struct DeltaPlan {
+    /// Installed copy for each SHA-256 the target needs
+    sources: HashMap<String, PathBuf>,
+    
+    /// Installed version sharing the most bytes with the target; deltas are published against it
+    pub base: Option<String>,
+    
+    /// Directory of `base`
+    base_dir: Option<PathBuf>,
+    
+    /// Target files (paths) found in no installed version
+    pub missing: Vec<String>,
+    
+    pub reused: usize,
+    pub reused_bytes: u64,
+    pub missing_bytes: u64,
+}
+
+/// Match the files of `target` against the manifests of every installed version
+pub fn plan(target: &Manifest) -> DeltaPlan {
+    let mut versions = Vec::new();
+    for store in [Store::User, Store::System] {
+        let Ok(root) = config::store_root(store) else {
+            continue;
+        };
+        for version in config::versions_in(&root) {
+            let dir = root.join(&version);
+            versions.push((version, dir));
+        }
+    }
+    plan_from(target, &versions)
+}
+
+/// Match the files of `target` against `versions` (name and directory, oldest first)
+fn plan_from(target: &Manifest, versions: &[(String, PathBuf)]) -> DeltaPlan {
+    // Regular files of every installed version, by content
+    let mut installed = Vec::new();
+    for (version, dir) in versions {
+        // Versions without a manifest (or with a broken one) are simply not reused
+        let Ok(Some(manifest)) = Manifest::load(dir) else {
+            continue;
+        };
+        let files: HashMap<_, _> = manifest
+            .files
+            .into_iter()
+            .filter(|f| f.link.is_none())
+            .map(|f| ((f.sha256, f.size), dir.join(&f.path)))
+            .collect();
+        installed.push((version.clone(), dir.clone(), files));
+    }
+    
+    let mut plan = DeltaPlan::default();
+    let mut shared: HashMap<&str, u64> = HashMap::new();
+    for entry in &target.files {
+        // Symlinks and empty files are recreated from the manifest alone
+        if entry.link.is_some() || entry.size == 0 {
+            continue;
+        }
+        let key = (entry.sha256.clone(), entry.size);
+        let mut found = false;
+        for (version, _, files) in &installed {
+            let Some(copy) = files.get(&key) else {
+                continue;
+            };
+            plan.sources
+                .entry(entry.sha256.clone())
+                .or_insert_with(|| copy.clone());
+            *shared.entry(version.as_str()).or_default() += entry.size;
+            found = true;
+        }
+        if found {
+            plan.reused += 1;
+            plan.reused_bytes += entry.size;
+        } else {
+            plan.missing.push(entry.path.clone());
+            plan.missing_bytes += entry.size;
+        }
+    }
+    
+    // Ties go to the newest version, the usual upgrade path
+    let base = installed
+        .iter()
+        .rev()
+        .filter_map(|(version, dir, _)| {
+            shared.get(version.as_str()).map(|bytes| (version, dir, *bytes))
+        })
+        .fold(None, |best: Option<(&String, &PathBuf, u64)>, (version, dir, bytes)| match best {
+            Some((_, _, most)) if most >= bytes => best,
+            _ => Some((version, dir, bytes)),
+        });
+    if let Some((version, dir, _)) = base {
+        plan.base = Some(version.clone());
+        plan.base_dir = Some(dir.clone());
+    }
+    
+    plan
+}
+
+/// Populate `staging` with the files of `target`: unchanged files are copied
+/// from installed versions, changed ones taken from the extracted delta archive.
+/// The result is then checked against `target`, so nothing unverified is installed.
+pub fn assemble(target: &Manifest, plan: &DeltaPlan, delta_dir: Option<&Path>, staging: &Path) -> Result<()> {
+    fs::create_dir_all(staging)
+        .with_context(|| format!("Failed to create {}", staging.display()))?;
+    let staging = &staging
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", staging.display()))?;
+    
+    for entry in &target.files {
+        // The manifest comes from the network: hold it to the same rules as archive entries
+        let rel = archive::safe_relative_path(Path::new(&entry.path))?;
+        let dest = staging.join(&rel);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        
+        if let Some(link) = &entry.link {
+            archive::check_link_target(staging, &rel, Path::new(link))?;
+            create_symlink(link, &dest)?;
+            continue;
+        }
+        
+        if entry.size == 0 {
+            File::create(&dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+        } else {
+            let source = match plan.sources.get(&entry.sha256) {
+                Some(source) => source.clone(),
+                None => delta_dir
+                    .map(|dir| dir.join(&rel))
+                    .filter(|path| path.is_file())
+                    .with_context(|| {
+                        format!("{} is neither installed nor in the delta archive", entry.path)
+                    })?,
+            };
+            fs::copy(&source, &dest)
+                .with_context(|| format!("Failed to copy {}", source.display()))?;
+        }
+        set_mode(&dest, entry.mode)?;
+    }
+    
+    // Manifests list no directories; keep the empty standard ones the base version has
+    if let Some(base_dir) = &plan.base_dir {
+        for path in manifest::required_paths() {
+            if base_dir.join(path).is_dir() {
+                fs::create_dir_all(staging.join(path))?;
+            }
+        }
+    }
+    
+    let report = target.verify(staging)?;
+    if !report.missing.is_empty() || !report.modified.is_empty() || !report.extra.is_empty() {
+        bail!(
+            "The assembled SDK does not match the published manifest ({} missing, {} modified, {} extra)",
+            report.missing.len(),
+            report.modified.len(),
+            report.extra.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, path)
+        .with_context(|| format!("Failed to create symlink {}", path.display()))
+}
+
+#[cfg(not(unix))]
+fn create_symlink(_target: &str, path: &Path) -> Result<()> {
+    bail!("Cannot recreate symlink {} on this platform", path.display())
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: Option<u32>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    if let Some(mode) = mode {
+        // Only permission bits: a crafted manifest must not make anything setuid, setgid or sticky
+        fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+            .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: Option<u32>) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Empty scratch directory for one test
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("mpf-dev-delta-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+    
+    /// Version directory holding `files`, with its manifest
+    fn version(root: &Path, name: &str, files: &[(&str, &str)]) -> (String, PathBuf) {
+        let dir = root.join(name);
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        Manifest::build(&dir, name, None).unwrap().save(&dir).unwrap();
+        (name.to_string(), dir)
+    }
+    
+    /// Published manifest of a release with `files`, built the way releases are
+    fn release(root: &Path, name: &str, files: &[(&str, &str)]) -> Manifest {
+        let (_, dir) = version(&root.join("release"), name, files);
+        Manifest::build(&dir, name, None).unwrap()
+    }
+    
+    #[test]
+    fn files_are_reused_by_hash_and_size() {
+        let root = scratch("reuse");
+        let installed = [version(
+            &root,
+            "v1.0.0",
+            &[("lib/a.so", "aaaa"), ("bin/mpf-host", "host1")],
+        )];
+        let target = release(
+            &root,
+            "v1.1.0",
+            &[("lib/renamed.so", "aaaa"), ("bin/mpf-host", "host2"), ("empty", "")],
+        );
+        
+        let plan = plan_from(&target, &installed);
+        assert_eq!(plan.base.as_deref(), Some("v1.0.0"));
+        assert_eq!(plan.reused, 1);
+        assert_eq!(plan.reused_bytes, 4);
+        assert_eq!(plan.missing, vec!["bin/mpf-host".to_string()]);
+        assert_eq!(plan.missing_bytes, 5);
+        
+        // The changed file comes from the delta, the rest from v1.0.0
+        let delta = root.join("delta");
+        fs::create_dir_all(delta.join("bin")).unwrap();
+        fs::write(delta.join("bin/mpf-host"), "host2").unwrap();
+        let staging = root.join("staging");
+        assemble(&target, &plan, Some(&delta), &staging).unwrap();
+        assert_eq!(fs::read_to_string(staging.join("lib/renamed.so")).unwrap(), "aaaa");
+        assert_eq!(fs::read_to_string(staging.join("bin/mpf-host")).unwrap(), "host2");
+        let _ = fs::remove_dir_all(&root);
+    }
+    
+    #[test]
+    fn same_contents_with_another_size_are_not_reused() {
+        let root = scratch("size");
+        let installed = [version(&root, "v1.0.0", &[("a", "aaaa")])];
+        let mut target = release(&root, "v1.1.0", &[("a", "aaaa")]);
+        target.files[0].size += 1;
+        
+        let plan = plan_from(&target, &installed);
+        assert_eq!(plan.reused, 0);
+        assert_eq!(plan.base, None);
+        let _ = fs::remove_dir_all(&root);
+    }
+    
+    #[test]
+    fn ties_go_to_the_newest_version() {
+        let root = scratch("tie");
+        let installed = [
+            version(&root, "v1.0.0", &[("a", "shared")]),
+            version(&root, "v1.1.0", &[("a", "shared")]),
+            version(&root, "v0.9.0", &[("b", "other")]),
+        ];
+        let target = release(&root, "v1.2.0", &[("a", "shared"), ("c", "new")]);
+        assert_eq!(plan_from(&target, &installed).base.as_deref(), Some("v1.1.0"));
+        
+        // More shared bytes win over being newer
+        let installed = [
+            version(&root, "v2.0.0", &[("a", "shared"), ("c", "new")]),
+            installed[1].clone(),
+        ];
+        assert_eq!(plan_from(&target, &installed).base.as_deref(), Some("v2.0.0"));
+        let _ = fs::remove_dir_all(&root);
+    }
+    
+    #[test]
+    fn missing_file_without_delta_fails() {
+        let root = scratch("no-delta");
+        let installed = [version(&root, "v1.0.0", &[("a", "aaaa")])];
+        let target = release(&root, "v1.1.0", &[("a", "aaaa"), ("b", "new")]);
+        let plan = plan_from(&target, &installed);
+        
+        assert!(assemble(&target, &plan, None, &root.join("staging")).is_err());
+        let empty_delta = root.join("delta");
+        fs::create_dir_all(&empty_delta).unwrap();
+        assert!(assemble(&target, &plan, Some(&empty_delta), &root.join("staging2")).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+    
+    #[test]
+    fn escaping_manifest_entries_are_rejected() {
+        let root = scratch("escape");
+        let installed = [version(&root, "v1.0.0", &[("a", "aaaa")])];
+        for path in ["../evil", "lib/../../evil", "/tmp/evil"] {
+            let mut target = release(&root, "v1.1.0", &[("a", "aaaa")]);
+            target.files[0].path = path.to_string();
+            let plan = plan_from(&target, &installed);
+            let result = assemble(&target, &plan, None, &root.join("staging"));
+            assert!(result.is_err(), "{} accepted", path);
+        }
+        assert!(!root.join("evil").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+    
+    #[test]
+    fn assembled_files_must_match_the_manifest() {
+        let root = scratch("mismatch");
+        let installed = [version(&root, "v1.0.0", &[("a", "aaaa")])];
+        let target = release(&root, "v1.1.0", &[("a", "aaaa"), ("b", "good")]);
+        let plan = plan_from(&target, &installed);
+        
+        // Same size, other contents
+        let delta = root.join("delta");
+        fs::create_dir_all(&delta).unwrap();
+        fs::write(delta.join("b"), "evil").unwrap();
+        assert!(assemble(&target, &plan, Some(&delta), &root.join("staging")).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn special_mode_bits_are_dropped() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let root = scratch("mode");
+        let path = root.join("mpf-host");
+        fs::write(&path, "host").unwrap();
+        set_mode(&path, Some(0o4755)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o7777, 0o755);
+        set_mode(&path, Some(0o7644)).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o7777, 0o644);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::config::{DevConfig, NetworkConfig};
+
+/// Default time allowed to establish a connection
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// Default time a read may stall before the request fails
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// The HTTP client shared by every network operation, built on first use
+/// from the `network` section of dev.json.
+///
+/// Proxies come from `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY` and `NO_PROXY`
+/// unless dev.json sets `network.proxy`, which takes precedence.
+pub fn client() -> Result<&'static reqwest::Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+    let network = DevConfig::load()?.network.unwrap_or_default();
+    let client = build_client(&network)?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+fn build_client(network: &NetworkConfig) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(
+            network.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        ))
+        .read_timeout(Duration::from_secs(
+            network.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT_SECS),
+        ));
+    
+    if let Some(proxy_url) = network.proxy.as_deref().filter(|p| !p.is_empty()) {
+        let no_proxy = network
+            .no_proxy
+            .clone()
+            .or_else(|| std::env::var("NO_PROXY").ok())
+            .or_else(|| std::env::var("no_proxy").ok());
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .with_context(|| format!("Invalid proxy URL '{}' in dev.json", proxy_url))?
+            .no_proxy(no_proxy.as_deref().and_then(reqwest::NoProxy::from_string));
+        builder = builder.proxy(proxy);
+    }
+    
+    for path in &network.ca_certs {
+        let pem = fs::read(path)
+            .with_context(|| format!("Failed to read CA bundle {}", path))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid PEM CA bundle {}", path))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    
+    builder.build().context("Failed to create HTTP client")
+}
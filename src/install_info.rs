@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Install record stored in every version directory
+pub const INSTALL_INFO_FILE: &str = ".mpf-install.json";
+
+/// How a version got onto disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallMethod {
+    /// Release archive downloaded from the release source
+    Download,
+    
+    /// Archive from the download cache
+    Cache,
+    
+    /// Assembled from installed versions plus a delta archive
+    Delta,
+    
+    /// Local archive (`setup --from-file`)
+    File,
+}
+
+impl std::fmt::Display for InstallMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallMethod::Download => write!(f, "download"),
+            InstallMethod::Cache => write!(f, "cache"),
+            InstallMethod::Delta => write!(f, "delta"),
+            InstallMethod::File => write!(f, "file"),
+        }
+    }
+}
+
+/// Where an installed version came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallInfo {
+    pub method: InstallMethod,
+    
+    /// URL or local path the SDK was installed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    
+    /// Release asset the version was installed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    
+    /// SHA-256 of the archive (none for delta installs, which have no single archive)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    
+    /// Installed version a delta install reused files from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    
+    /// Unix timestamp of the install
+    pub installed_at: u64,
+    
+    /// Total size of the SDK files in bytes
+    pub size: u64,
+}
+
+impl InstallInfo {
+    /// Record for an install happening now; the size is filled in once the files are in place
+    pub fn new(
+        method: InstallMethod,
+        source: Option<String>,
+        asset: Option<&str>,
+        sha256: Option<String>,
+    ) -> Self {
+        Self {
+            method,
+            source,
+            asset: asset.map(|a| a.to_string()),
+            sha256: sha256.map(|h| h.to_lowercase()),
+            base: None,
+            installed_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            size: 0,
+        }
+    }
+    
+    /// Record of a version directory; versions installed before records existed have none
+    pub fn load(dir: &Path) -> Option<Self> {
+        let content = fs::read_to_string(dir.join(INSTALL_INFO_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+    
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(INSTALL_INFO_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use std::fs::{self, File, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use crate::config::{self, Store};
+
+/// Lock file at the top of each SDK store
+const LOCK_FILE: &str = ".lock";
+
+/// Advisory lock on the SDK root, held while a command changes it.
+/// The OS drops it when the process exits, so a crashed run never leaves it behind.
+pub struct SdkLock {
+    _files: Vec<File>,
+}
+
+/// Lock the user's SDK root, and the system store as well with `--system`.
+/// If another mpf-dev process holds it, wait for it, or fail right away with `no_wait`.
+pub fn acquire(no_wait: bool) -> Result<SdkLock> {
+    // Always the user's root first, so two processes can never wait on each other
+    let mut files = vec![lock_store(&config::sdk_root()?, Store::User, no_wait)?];
+    if config::system_store_selected() {
+        files.push(lock_store(&config::store_root(Store::System)?, Store::System, no_wait)?);
+    }
+    Ok(SdkLock { _files: files })
+}
+
+fn lock_store(root: &Path, store: Store, no_wait: bool) -> Result<File> {
+    let path = root.join(LOCK_FILE);
+    let hint = || match store {
+        Store::User => format!("Failed to open lock file {}", path.display()),
+        Store::System => format!(
+            "Cannot lock the system SDK store {} (run as an administrator, or set MPF_SDK_SYSTEM_HOME)",
+            root.display()
+        ),
+    };
+    fs::create_dir_all(root).with_context(hint)?;
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(hint)?;
+    
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            let holder = holder(&mut file);
+            if no_wait {
+                bail!(
+                    "Another mpf-dev process{} is modifying {}; try again once it has finished",
+                    holder,
+                    root.display()
+                );
+            }
+            println!(
+                "{} Waiting for another mpf-dev process{} to finish with {}...",
+                "Note:".yellow(),
+                holder,
+                root.display()
+            );
+            file.lock()
+                .with_context(|| format!("Failed to lock {}", path.display()))?;
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+        }
+    }
+    
+    // Leave our pid for whoever has to wait next
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", std::process::id())?;
+    Ok(file)
+}
+
+/// " (pid N)" for the process holding the lock, if it left its pid
+fn holder(file: &mut File) -> String {
+    let mut pid = String::new();
+    let _ = file.read_to_string(&mut pid);
+    match pid.trim() {
+        "" => String::new(),
+        pid => format!(" (pid {})", pid),
+    }
+}
@@ -1,5 +1,18 @@
+mod archive;
+mod asset;
+mod cache;
 mod config;
+mod dedupe;
+mod delta;
+mod http;
+mod install_info;
+mod lock;
+mod manifest;
 mod commands;
+mod release;
+mod self_update;
+mod signature;
+mod version;
 
 use clap::{Parser, Subcommand};
 use anyhow::Result;
@@ -9,6 +22,30 @@ use anyhow::Result;
 #[command(about = "MPF Development Environment CLI Tool")]
 #[command(version)]
 struct Cli {
+    /// Release API base URL (env: MPF_RELEASE_API_URL)
+    #[arg(long, global = true, value_name = "URL")]
+    release_api_url: Option<String>,
+    
+    /// Release download base URL (env: MPF_RELEASE_DOWNLOAD_URL)
+    #[arg(long, global = true, value_name = "URL")]
+    release_download_url: Option<String>,
+    
+    /// Release repository slug, e.g. owner/repo (env: MPF_RELEASE_REPO)
+    #[arg(long, global = true, value_name = "OWNER/REPO")]
+    release_repo: Option<String>,
+    
+    /// SDK root directory (env: MPF_SDK_HOME, default: ~/.mpf-sdk)
+    #[arg(long, global = true, value_name = "DIR")]
+    sdk_root: Option<std::path::PathBuf>,
+    
+    /// Install into, remove from and repair the shared system store instead of the user's
+    #[arg(long, global = true)]
+    system: bool,
+    
+    /// Fail instead of waiting when another mpf-dev process is modifying the SDK root
+    #[arg(long, global = true)]
+    no_wait: bool,
+    
     #[command(subcommand)]
     command: Commands,
 }
@@ -17,18 +54,140 @@ struct Cli {
 enum Commands {
     /// Download and install MPF SDK
     Setup {
-        /// SDK version to install (default: latest)
+        /// SDK version to install: exact (1.2.3), range (^1.2, ~1.2.3), latest or latest-patch
+        /// (default: latest; with --from-file, the version label)
         #[arg(short, long)]
         version: Option<String>,
+        
+        /// Release channel used to resolve ranges and latest
+        #[arg(long, value_enum, default_value_t = version::Channel::Stable)]
+        channel: version::Channel,
+        
+        /// Install from a local .tar.gz or .zip archive instead of downloading
+        #[arg(long, value_name = "PATH")]
+        from_file: Option<String>,
+        
+        /// Release asset to download, overriding platform detection
+        #[arg(long, value_name = "NAME", conflicts_with = "from_file")]
+        asset: Option<String>,
+        
+        /// Qt flavour the asset must be built for (e.g. gcc_64, mingw_64, msvc2019_64)
+        #[arg(long, value_name = "FLAVOUR", conflicts_with = "from_file")]
+        qt: Option<String>,
+        
+        /// Skip checksum and signature verification of the archive
+        #[arg(long)]
+        skip_verify: bool,
+        
+        /// Install the version named by the nearest .mpf-version (without changing the global version)
+        #[arg(long, conflicts_with_all = ["version", "from_file"])]
+        locked: bool,
+        
+        /// Also install the SDK's debug symbols (used by gdb/lldb under `run` and `exec`)
+        #[arg(long, conflicts_with = "from_file")]
+        with_symbols: bool,
+        
+        /// Also install the sources the SDK was built from
+        #[arg(long, requires = "with_symbols")]
+        with_sources: bool,
     },
     
     /// List installed SDK versions
-    Versions,
+    Versions {
+        /// List releases available from the release source
+        #[arg(long)]
+        remote: bool,
+        
+        /// Use the cached release index instead of the network
+        #[arg(long, requires = "remote")]
+        offline: bool,
+        
+        /// Show where each version was installed from, when, and its disk usage
+        #[arg(short, long, conflicts_with = "remote")]
+        long: bool,
+        
+        /// Print installed versions and their install records as JSON
+        #[arg(long, conflicts_with_all = ["remote", "long"])]
+        json: bool,
+    },
+    
+    /// Show release notes between two versions (e.g. 1.0.0..1.2.0)
+    Changelog {
+        /// Version range <from>..<to>; omit <to> for the newest release
+        range: String,
+        
+        /// Use the cached release index instead of the network
+        #[arg(long)]
+        offline: bool,
+    },
+    
+    /// Upgrade the current SDK to the newest compatible release
+    Update {
+        /// Release channel (default: `update.channel` in dev.json, else stable)
+        #[arg(long, value_enum)]
+        channel: Option<version::Channel>,
+        
+        /// Allow moving to a new major version
+        #[arg(long)]
+        major: bool,
+        
+        /// Only show what would be installed
+        #[arg(long)]
+        check: bool,
+    },
+    
+    /// Update mpf-dev itself to its newest release
+    SelfUpdate {
+        /// Only check; exits with code 10 when an update is available
+        #[arg(long, conflicts_with = "rollback")]
+        check: bool,
+        
+        /// Restore the executable replaced by the last self-update
+        #[arg(long)]
+        rollback: bool,
+    },
     
     /// Switch to a specific SDK version
     Use {
-        /// Version to use
+        /// Version to use: exact, range (^1.2) or latest; ranges pick the highest installed match
+        version: String,
+        
+        /// Release channel used to resolve ranges and latest among installed versions
+        #[arg(long, value_enum, default_value_t = version::Channel::Stable)]
+        channel: version::Channel,
+        
+        /// Pin the version for this project by writing .mpf-version in the current directory
+        #[arg(long)]
+        local: bool,
+    },
+    
+    /// Remove an installed SDK version
+    Remove {
+        /// Version to remove
         version: String,
+        
+        /// Remove even if it is the current version
+        #[arg(long)]
+        force: bool,
+    },
+    
+    /// Remove old SDK versions (the current and pinned versions are always kept)
+    Gc {
+        /// Number of newest versions to keep
+        #[arg(long, default_value_t = 2)]
+        keep: usize,
+    },
+    
+    /// Inspect and maintain installed SDK versions
+    Sdk {
+        #[command(subcommand)]
+        action: SdkAction,
+    },
+    
+    /// Manage downloaded SDK archives (~/.mpf-sdk/cache)
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
     },
     
     /// Link a component for source development
@@ -55,11 +214,22 @@ enum Commands {
         #[arg(short, long)]
         debug: bool,
         
+        /// Start mpf-host under a debugger (gdb or lldb) with the SDK's symbols and sources
+        #[arg(long, value_name = "PROGRAM")]
+        debugger: Option<String>,
+        
         /// Additional arguments to pass to mpf-host
         #[arg(last = true)]
         args: Vec<String>,
     },
     
+    /// Run a command with the SDK environment (e.g. mpf-dev exec cmake -B build)
+    Exec {
+        /// Command and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    
     /// Manage full-source workspace (all components from source)
     Workspace {
         #[command(subcommand)]
@@ -67,6 +237,44 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum SdkAction {
+    /// Check an installed version against its manifest (missing, modified and extra files)
+    Verify {
+        /// Version to check (default: current)
+        version: Option<String>,
+        
+        /// Fix problems: delete extra files, re-download the version if files are missing or modified
+        #[arg(long)]
+        repair: bool,
+    },
+    
+    /// Replace files that are identical across installed versions with hard links
+    Dedupe,
+    
+    /// Show an installed SDK's version header, the Qt version it was built against and its install record
+    Info {
+        /// Version to describe (default: current)
+        version: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached archives
+    List,
+    
+    /// Delete all cached archives
+    Clean,
+    
+    /// Delete least recently used archives until the cache fits a size limit
+    Prune {
+        /// Size limit, e.g. 2G or 500M
+        #[arg(long, value_name = "SIZE")]
+        max_size: String,
+    },
+}
+
 #[derive(Subcommand)]
 enum LinkAction {
     /// Link a plugin build output (auto-derives lib, qml, plugin paths)
@@ -140,19 +348,117 @@ enum WorkspaceAction {
     Status,
 }
 
+impl Commands {
+    /// Whether the command writes to the SDK root and has to hold its lock
+    fn mutates_sdk_root(&self) -> bool {
+        match self {
+            Commands::Setup { .. }
+            | Commands::Use { .. }
+            | Commands::Remove { .. }
+            | Commands::Gc { .. }
+            | Commands::Link { .. }
+            | Commands::Unlink { .. } => true,
+            Commands::Update { check, .. } => !check,
+            Commands::SelfUpdate { check, .. } => !check,
+            Commands::Sdk { action } => match action {
+                SdkAction::Verify { repair, .. } => *repair,
+                SdkAction::Dedupe => true,
+                SdkAction::Info { .. } => false,
+            },
+            Commands::Cache { action } => !matches!(action, CacheAction::List),
+            Commands::Versions { .. }
+            | Commands::Changelog { .. }
+            | Commands::Status
+            | Commands::Env
+            | Commands::Run { .. }
+            | Commands::Exec { .. }
+            | Commands::Workspace { .. } => false,
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    if let Some(root) = cli.sdk_root {
+        config::set_sdk_root(root);
+    }
+    if cli.system {
+        config::select_system_store();
+    }
+    let source = release::ReleaseSource::resolve(
+        cli.release_api_url,
+        cli.release_download_url,
+        cli.release_repo,
+    );
+    
+    // Held until the command finishes
+    let _lock = if cli.command.mutates_sdk_root() {
+        Some(lock::acquire(cli.no_wait)?)
+    } else {
+        None
+    };
     
     match cli.command {
-        Commands::Setup { version } => commands::setup(version).await,
-        Commands::Versions => commands::versions(),
-        Commands::Use { version } => commands::use_version(&version),
+        Commands::Setup {
+            version,
+            channel,
+            from_file,
+            asset,
+            qt,
+            skip_verify,
+            locked,
+            with_symbols,
+            with_sources,
+        } => {
+            let mut selector = asset::AssetSelector::new(asset, qt);
+            if with_symbols {
+                selector.companions.push(asset::Companion::Symbols);
+            }
+            if with_sources {
+                selector.companions.push(asset::Companion::Sources);
+            }
+            commands::setup(&source, version, channel, from_file, selector, skip_verify, locked)
+                .await
+        }
+        Commands::Versions { remote, offline, long, json } => {
+            commands::versions(&source, remote, offline, long, json).await
+        }
+        Commands::Changelog { range, offline } => commands::changelog(&source, &range, offline).await,
+        Commands::Update { channel, major, check } => {
+            commands::update(&source, channel, major, check).await
+        }
+        Commands::SelfUpdate { check, rollback } => {
+            commands::self_update(check, rollback).await
+        }
+        Commands::Use { version, channel, local } => commands::use_version(&version, channel, local),
+        Commands::Remove { version, force } => commands::remove(&version, force),
+        Commands::Gc { keep } => commands::gc(keep),
+        Commands::Sdk { action } => match action {
+            SdkAction::Verify { version, repair } => {
+                commands::sdk_verify(&source, version, repair).await
+            }
+            SdkAction::Dedupe => commands::sdk_dedupe(),
+            SdkAction::Info { version } => commands::sdk_info(version),
+        },
+        Commands::Cache { action } => match action {
+            CacheAction::List => commands::cache_list(),
+            CacheAction::Clean => commands::cache_clean(),
+            CacheAction::Prune { max_size } => commands::cache_prune(&max_size),
+        },
         Commands::Link { action } => commands::link_action(action),
         Commands::Unlink { component } => commands::unlink(&component),
-        Commands::Status => commands::status(),
+        Commands::Status => {
+            commands::status()?;
+            commands::update_notice(&source).await;
+            Ok(())
+        }
         Commands::Env => commands::env_vars(),
-        Commands::Run { debug, args } => commands::run(debug, args),
+        Commands::Run { debug, debugger, args } => {
+            commands::update_notice(&source).await;
+            commands::run(debug, debugger, args)
+        }
+        Commands::Exec { command } => commands::exec(command),
         Commands::Workspace { action } => match action {
             WorkspaceAction::Init { path } => commands::workspace_init(path),
             WorkspaceAction::Build { config } => commands::workspace_build(&config),
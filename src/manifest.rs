@@ -0,0 +1,264 @@
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Integrity manifest stored in every installed version directory
+pub const MANIFEST_FILE: &str = ".mpf-manifest.json";
+
+/// mpf-dev's own files and directories in a version directory start with this and are not part of the SDK
+pub const INTERNAL_PREFIX: &str = ".mpf-";
+
+/// Entries every SDK must contain
+pub fn required_paths() -> [&'static str; 5] {
+    let host = if cfg!(windows) { "bin/mpf-host.exe" } else { "bin/mpf-host" };
+    [host, "lib", "qml", "include", "plugins"]
+}
+
+/// Files of an installed SDK version as extracted from the release archive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: String,
+    
+    /// Release asset the version was installed from (reused by `sdk verify --repair`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+    
+    /// Unix timestamp of the install
+    pub created_at: u64,
+    
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileEntry {
+    /// Path relative to the version directory, `/`-separated
+    pub path: String,
+    
+    #[serde(default)]
+    pub size: u64,
+    
+    /// Lowercase hex SHA-256 (empty for symlinks)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sha256: String,
+    
+    /// Unix permission bits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    
+    /// Symlink target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// Differences between a version directory and its manifest
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub missing: Vec<String>,
+    pub modified: Vec<String>,
+    pub extra: Vec<String>,
+    
+    /// Required SDK entries (see `required_paths`) that do not exist
+    pub missing_layout: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty()
+            && self.modified.is_empty()
+            && self.extra.is_empty()
+            && self.missing_layout.is_empty()
+    }
+    
+    /// Whether fixing the version needs the original files (extra files can simply be deleted)
+    pub fn needs_download(&self) -> bool {
+        !self.missing.is_empty() || !self.modified.is_empty() || !self.missing_layout.is_empty()
+    }
+}
+
+impl Manifest {
+    /// Record every file under `dir`
+    pub fn build(dir: &Path, version: &str, asset: Option<&str>) -> Result<Self> {
+        let files = list_files(dir)?
+            .iter()
+            .map(|rel| file_entry(dir, rel))
+            .collect::<Result<Vec<_>>>()?;
+        
+        Ok(Self {
+            version: version.to_string(),
+            asset: asset.map(|a| a.to_string()),
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            files,
+        })
+    }
+    
+    /// Load the manifest of a version directory, if it has one
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+    
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+    
+    /// Compare `dir` against the manifest
+    pub fn verify(&self, dir: &Path) -> Result<VerifyReport> {
+        let mut report = VerifyReport {
+            missing_layout: missing_layout(dir),
+            ..Default::default()
+        };
+        
+        let pb = ProgressBar::new(self.files.len() as u64);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {wide_msg}")?
+                .progress_chars("#>-"),
+        );
+        
+        for expected in &self.files {
+            pb.set_message(expected.path.clone());
+            pb.inc(1);
+            let path = dir.join(&expected.path);
+            if fs::symlink_metadata(&path).is_err() {
+                report.missing.push(expected.path.clone());
+                continue;
+            }
+            let actual = file_entry(dir, Path::new(&expected.path))?;
+            if !entry_matches(expected, &actual) {
+                report.modified.push(expected.path.clone());
+            }
+        }
+        pb.finish_and_clear();
+        
+        let known: HashSet<&str> = self.files.iter().map(|f| f.path.as_str()).collect();
+        report.extra = list_files(dir)?
+            .iter()
+            .map(|rel| slash_path(rel))
+            .filter(|p| !known.contains(p.as_str()))
+            .collect();
+        
+        Ok(report)
+    }
+}
+
+/// Required SDK entries missing from `dir`
+pub fn missing_layout(dir: &Path) -> Vec<String> {
+    required_paths()
+        .iter()
+        .filter(|p| !dir.join(p).exists())
+        .map(|p| p.to_string())
+        .collect()
+}
+
+fn entry_matches(expected: &FileEntry, actual: &FileEntry) -> bool {
+    if expected.link.is_some() || actual.link.is_some() {
+        return expected.link == actual.link;
+    }
+    // Permission bits are only meaningful where both sides recorded them
+    let mode_ok = match (expected.mode, actual.mode) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    };
+    expected.size == actual.size && expected.sha256 == actual.sha256 && mode_ok
+}
+
+fn file_entry(dir: &Path, rel: &Path) -> Result<FileEntry> {
+    let path = dir.join(rel);
+    let meta = fs::symlink_metadata(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    
+    if meta.is_symlink() {
+        let target = fs::read_link(&path)?;
+        return Ok(FileEntry {
+            path: slash_path(rel),
+            size: 0,
+            sha256: String::new(),
+            mode: None,
+            link: Some(target.to_string_lossy().to_string()),
+        });
+    }
+    
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        Some(meta.permissions().mode() & 0o777)
+    };
+    #[cfg(not(unix))]
+    let mode = None;
+    
+    Ok(FileEntry {
+        path: slash_path(rel),
+        size: meta.len(),
+        sha256: sha256_file(&path)?,
+        mode,
+        link: None,
+    })
+}
+
+/// Files and symlinks under `dir` as sorted relative paths, without mpf-dev's own files
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(root: &Path, rel: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+        let path = root.join(rel);
+        for entry in fs::read_dir(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+        {
+            let entry = entry?;
+            if rel.as_os_str().is_empty()
+                && entry.file_name().to_string_lossy().starts_with(INTERNAL_PREFIX)
+            {
+                continue;
+            }
+            let child = rel.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                walk(root, &child, out)?;
+            } else {
+                out.push(child);
+            }
+        }
+        Ok(())
+    }
+    
+    let mut files = Vec::new();
+    walk(dir, Path::new(""), &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn slash_path(rel: &Path) -> String {
+    rel.to_string_lossy().replace('\\', "/")
+}
+
+/// Compute the lowercase hex SHA-256 of a file
+pub fn sha256_file(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
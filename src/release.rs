@@ -0,0 +1,587 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::{self, DevConfig};
+use crate::version::Channel;
+
+/// Default GitHub API base URL
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Default base URL for release asset downloads
+pub const DEFAULT_DOWNLOAD_URL: &str = "https://github.com";
+
+/// Default repository publishing SDK releases
+pub const DEFAULT_REPO: &str = "dyzdyz010/mpf-release";
+
+/// Where SDK releases are published.
+///
+/// URLs are built the way GitHub lays them out, so the same shape works for
+/// github.com, GitHub Enterprise, static HTTP mirrors and `file://` directories:
+/// - API:       `<api_url>/repos/<repo>/releases/latest`
+/// - Downloads: `<download_url>/<repo>/releases/download/<tag>/<asset>`
+#[derive(Debug, Clone)]
+pub struct ReleaseSource {
+    pub api_url: String,
+    pub download_url: String,
+    pub repo: String,
+    
+    /// GitHub token sent with API and asset requests (raises the rate limit,
+    /// required for private release repositories)
+    pub token: Option<String>,
+    
+    /// Whether the token also goes to the download server. Only a token set in
+    /// dev.json next to the source is meant for it; one from the environment is
+    /// a GitHub credential and stays with the API server.
+    pub token_for_downloads: bool,
+}
+
+impl ReleaseSource {
+    /// Resolve the release source.
+    /// Priority: CLI flag > environment variable > dev.json `release` > built-in default
+    pub fn resolve(
+        api_url: Option<String>,
+        download_url: Option<String>,
+        repo: Option<String>,
+    ) -> Self {
+        let configured = DevConfig::load()
+            .ok()
+            .and_then(|c| c.release)
+            .unwrap_or_default();
+        
+        let env_token = env_token();
+        
+        let pick = |flag: Option<String>, var: &str, config: Option<String>, default: &str| {
+            flag.or_else(|| env::var(var).ok().filter(|v| !v.is_empty()))
+                .or(config)
+                .unwrap_or_else(|| default.to_string())
+                .trim_end_matches('/')
+                .to_string()
+        };
+        
+        Self {
+            api_url: pick(api_url, "MPF_RELEASE_API_URL", configured.api_url, DEFAULT_API_URL),
+            download_url: pick(
+                download_url,
+                "MPF_RELEASE_DOWNLOAD_URL",
+                configured.download_url,
+                DEFAULT_DOWNLOAD_URL,
+            ),
+            repo: pick(repo, "MPF_RELEASE_REPO", configured.repo, DEFAULT_REPO)
+                .trim_matches('/')
+                .to_string(),
+            token: env_token
+                .clone()
+                .or(configured.token)
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty()),
+            token_for_downloads: env_token.is_none(),
+        }
+    }
+    
+    /// URL of the "latest release" API document
+    pub fn latest_release_url(&self) -> String {
+        format!("{}/repos/{}/releases/latest", self.api_url, self.repo)
+    }
+    
+    /// URL of the release list API document
+    pub fn releases_url(&self) -> String {
+        format!("{}/repos/{}/releases", self.api_url, self.repo)
+    }
+    
+    /// URL of the API document for the release tagged `tag`
+    pub fn release_url(&self, tag: &str) -> String {
+        format!("{}/repos/{}/releases/tags/{}", self.api_url, self.repo, tag)
+    }
+    
+    /// Base URL that release assets for `tag` are downloaded from
+    pub fn release_download_base(&self, tag: &str) -> String {
+        format!("{}/{}/releases/download/{}", self.download_url, self.repo, tag)
+    }
+    
+    /// Download URL of a single release asset
+    pub fn asset_url(&self, tag: &str, asset: &str) -> String {
+        format!("{}/{}", self.release_download_base(tag), asset)
+    }
+    
+    /// URL to download `asset` from. With a token and GitHub's own download server,
+    /// the API asset endpoint from `release` is preferred: it is the only one that
+    /// serves private repositories. A configured download mirror is always kept.
+    pub fn asset_download_url(&self, release: Option<&ReleaseInfo>, tag: &str, asset: &str) -> String {
+        if self.token.is_some() && self.download_url == DEFAULT_DOWNLOAD_URL {
+            let api_url = release
+                .and_then(|r| r.assets.iter().find(|a| a.name == asset))
+                .and_then(|a| a.api_url.clone());
+            if let Some(url) = api_url {
+                return url;
+            }
+        }
+        self.asset_url(tag, asset)
+    }
+    
+    /// Whether a request to `url` carries the token: only requests to the API
+    /// server do, and to the download server when the token was configured for it,
+    /// so a mirror or third-party host never sees the credential
+    pub fn sends_token(&self, url: &str) -> bool {
+        let origin = |url: &str| reqwest::Url::parse(url).ok().map(|u| u.origin());
+        let Some(target) = origin(url).filter(|o| o.is_tuple()) else {
+            return false;
+        };
+        self.token.is_some()
+            && (origin(&self.api_url) == Some(target.clone())
+                || (self.token_for_downloads && origin(&self.download_url) == Some(target)))
+    }
+    
+    /// GET request with the User-Agent, the token (where it may go) and, for API
+    /// asset URLs, the `Accept` header that makes GitHub return the raw file
+    pub fn get(&self, client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+        let mut request = client.get(url).header("User-Agent", "mpf-dev");
+        if let Some(token) = self.token.as_ref().filter(|_| self.sends_token(url)) {
+            request = request.bearer_auth(token);
+        }
+        if url.starts_with(&self.api_url) && url.contains("/releases/assets/") {
+            request = request.header(reqwest::header::ACCEPT, "application/octet-stream");
+        }
+        request
+    }
+}
+
+/// Local path for a `file://` URL, or None for any other scheme
+pub fn file_url_path(url: &str) -> Option<PathBuf> {
+    if !url.starts_with("file:") {
+        return None;
+    }
+    reqwest::Url::parse(url).ok()?.to_file_path().ok()
+}
+
+/// Fetch a small text document, returning None when it does not exist (HTTP 404 or missing file)
+pub async fn fetch_optional_text(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    url: &str,
+) -> Result<Option<String>> {
+    if let Some(mut path) = file_url_path(url) {
+        // A directory stands for its index document (e.g. `releases/` next to `releases/latest`)
+        if path.is_dir() {
+            path.push("index.json");
+        }
+        if !path.exists() {
+            return Ok(None);
+        }
+        return fs::read_to_string(&path)
+            .map(Some)
+            .with_context(|| format!("Failed to read {}", path.display()));
+    }
+    
+    let resp = source.get(client, url).send().await?;
+    
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if let Some(e) = rate_limit_error(&resp, source.sends_token(url)) {
+        return Err(e);
+    }
+    if resp.status() == reqwest::StatusCode::UNAUTHORIZED && source.sends_token(url) {
+        bail!(
+            "Release source rejected the GitHub token (401 Unauthorized) for {}; check GITHUB_TOKEN, GH_TOKEN or `release.token` in dev.json",
+            url
+        );
+    }
+    if !resp.status().is_success() {
+        bail!("Failed to fetch {}: {}", url, resp.status());
+    }
+    Ok(Some(resp.text().await?))
+}
+
+/// Fetch and parse a JSON document
+pub async fn fetch_json(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    url: &str,
+) -> Result<serde_json::Value> {
+    let text = match fetch_optional_text(client, source, url).await? {
+        Some(text) => text,
+        // GitHub answers 404 for private repositories without a token
+        None if !source.sends_token(url) && file_url_path(url).is_none() => bail!(
+            "Not found: {} (private repositories need GITHUB_TOKEN or GH_TOKEN)",
+            url
+        ),
+        None => bail!("Not found: {}", url),
+    };
+    serde_json::from_str(&text).with_context(|| format!("Invalid JSON from {}", url))
+}
+
+/// Fetch the release tagged `tag`, or None if there is no such release
+pub async fn fetch_release(
+    client: &reqwest::Client,
+    source: &ReleaseSource,
+    tag: &str,
+) -> Result<Option<ReleaseInfo>> {
+    let url = source.release_url(tag);
+    let Some(text) = fetch_optional_text(client, source, &url).await? else {
+        return Ok(None);
+    };
+    let value: serde_json::Value =
+        serde_json::from_str(&text).with_context(|| format!("Invalid JSON from {}", url))?;
+    Ok(ReleaseInfo::from_api(&value))
+}
+
+/// Describe a GitHub rate-limit response (403/429 with `x-ratelimit-remaining: 0`
+/// or `retry-after`), or None for any other response
+pub fn rate_limit_error(resp: &reqwest::Response, authenticated: bool) -> Option<anyhow::Error> {
+    let status = resp.status();
+    if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let header = |name: &str| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok())
+    };
+    let remaining = header("x-ratelimit-remaining");
+    let retry_after = header("retry-after");
+    if remaining != Some(0) && retry_after.is_none() && status == reqwest::StatusCode::FORBIDDEN {
+        return None;
+    }
+    
+    let mut message = match header("x-ratelimit-limit") {
+        Some(limit) => format!("GitHub API rate limit exceeded ({} requests/hour)", limit),
+        None => "GitHub API rate limit exceeded".to_string(),
+    };
+    if let Some(seconds) = retry_after {
+        message.push_str(&format!("; retry in {}s", seconds));
+    } else if let Some(reset) = header("x-ratelimit-reset") {
+        message.push_str(&format!("; resets at {}", format_reset_time(reset)));
+    }
+    if !authenticated {
+        message.push_str(
+            ".\nSet GITHUB_TOKEN or GH_TOKEN (or `release.token` in dev.json) to raise the limit",
+        );
+    }
+    Some(anyhow::anyhow!(message))
+}
+
+/// "HH:MM UTC (in N min)" for a Unix timestamp
+fn format_reset_time(epoch: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let seconds_of_day = epoch % 86400;
+    format!(
+        "{:02}:{:02} UTC (in {} min)",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        epoch.saturating_sub(now).div_ceil(60)
+    )
+}
+
+/// GitHub token from `GITHUB_TOKEN` or `GH_TOKEN`
+pub fn env_token() -> Option<String> {
+    ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|v| !v.trim().is_empty()))
+}
+
+/// A published release as recorded in the release index
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReleaseInfo {
+    pub tag: String,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    
+    /// ISO 8601 publish timestamp
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<String>,
+    
+    #[serde(default)]
+    pub prerelease: bool,
+    
+    /// Release notes (Markdown)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    
+    #[serde(default)]
+    pub assets: Vec<AssetInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssetInfo {
+    pub name: String,
+    
+    #[serde(default)]
+    pub size: u64,
+    
+    /// Direct download URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    
+    /// API URL of the asset (used for authenticated downloads)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_url: Option<String>,
+}
+
+impl ReleaseInfo {
+    /// Convert a release object from the GitHub API
+    pub fn from_api(value: &serde_json::Value) -> Option<Self> {
+        let text = |v: &serde_json::Value| v.as_str().map(|s| s.to_string());
+        let assets = value["assets"]
+            .as_array()
+            .map(|assets| {
+                assets
+                    .iter()
+                    .filter_map(|a| {
+                        Some(AssetInfo {
+                            name: text(&a["name"])?,
+                            size: a["size"].as_u64().unwrap_or(0),
+                            download_url: text(&a["browser_download_url"]),
+                            api_url: text(&a["url"]),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        Some(Self {
+            tag: text(&value["tag_name"])?,
+            name: text(&value["name"]).filter(|s| !s.is_empty()),
+            published_at: text(&value["published_at"]),
+            prerelease: value["prerelease"].as_bool().unwrap_or(false),
+            body: text(&value["body"]).filter(|s| !s.trim().is_empty()),
+            assets,
+        })
+    }
+    
+    /// Publish date as YYYY-MM-DD
+    pub fn date(&self) -> &str {
+        self.published_at
+            .as_deref()
+            .map(|d| d.get(..10).unwrap_or(d))
+            .unwrap_or("-")
+    }
+    
+    pub fn has_asset(&self, name: &str) -> bool {
+        self.assets.iter().any(|a| a.name == name)
+    }
+}
+
+/// Cached list of releases from a release source (~/.mpf-sdk/releases.json)
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ReleaseIndex {
+    pub api_url: String,
+    pub repo: String,
+    
+    /// Unix timestamp of the last successful fetch
+    pub fetched_at: u64,
+    
+    pub releases: Vec<ReleaseInfo>,
+}
+
+/// Releases fetched per API page (GitHub maximum)
+const RELEASES_PER_PAGE: usize = 100;
+
+impl ReleaseIndex {
+    /// Fetch every published release from the source (drafts are skipped)
+    pub async fn fetch(client: &reqwest::Client, source: &ReleaseSource) -> Result<Self> {
+        let mut releases: Vec<ReleaseInfo> = Vec::new();
+        let is_file = file_url_path(&source.api_url).is_some();
+        
+        let mut page = 1;
+        loop {
+            // Static mirrors and file:// directories serve the whole list as one document
+            let url = if is_file {
+                source.releases_url()
+            } else {
+                format!("{}?per_page={}&page={}", source.releases_url(), RELEASES_PER_PAGE, page)
+            };
+            let value = fetch_json(client, source, &url).await?;
+            let items = value
+                .as_array()
+                .with_context(|| format!("Unexpected release list format from {}", url))?;
+            
+            let before = releases.len();
+            for item in items {
+                if item["draft"].as_bool().unwrap_or(false) {
+                    continue;
+                }
+                if let Some(info) = ReleaseInfo::from_api(item) {
+                    if !releases.iter().any(|r| r.tag == info.tag) {
+                        releases.push(info);
+                    }
+                }
+            }
+            
+            // Stop on a short page, or when a server ignoring `page` repeats itself
+            if is_file || items.len() < RELEASES_PER_PAGE || releases.len() == before {
+                break;
+            }
+            page += 1;
+        }
+        
+        Ok(Self {
+            api_url: source.api_url.clone(),
+            repo: source.repo.clone(),
+            fetched_at: unix_now(),
+            releases,
+        })
+    }
+    
+    /// Load the cached index, if any
+    pub fn load_cached() -> Option<Self> {
+        let content = fs::read_to_string(config::release_index_path().ok()?).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+    
+    pub fn save(&self) -> Result<()> {
+        let path = config::release_index_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        config::write_atomic(&path, serde_json::to_string_pretty(self)?)
+    }
+    
+    /// Whether this index was fetched from `source`
+    pub fn matches(&self, source: &ReleaseSource) -> bool {
+        self.api_url == source.api_url && self.repo == source.repo
+    }
+    
+    pub fn find(&self, tag: &str) -> Option<&ReleaseInfo> {
+        self.releases.iter().find(|r| r.tag == tag)
+    }
+}
+
+/// Result of the last "new SDK available" check (~/.mpf-sdk/update-check.json)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCheck {
+    pub api_url: String,
+    pub repo: String,
+    pub channel: Channel,
+    
+    /// Unix timestamp of the check
+    pub checked_at: u64,
+    
+    /// Newest release found, None if the check failed
+    pub latest: Option<String>,
+}
+
+impl UpdateCheck {
+    pub fn new(source: &ReleaseSource, channel: Channel, checked_at: u64, latest: Option<String>) -> Self {
+        Self {
+            api_url: source.api_url.clone(),
+            repo: source.repo.clone(),
+            channel,
+            checked_at,
+            latest,
+        }
+    }
+    
+    pub fn load() -> Option<Self> {
+        let content = fs::read_to_string(config::update_check_path().ok()?).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+    
+    pub fn save(&self) -> Result<()> {
+        let path = config::update_check_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        config::write_atomic(&path, serde_json::to_string_pretty(self)?)
+    }
+    
+    /// Whether this result applies to `source` and `channel`
+    pub fn matches(&self, source: &ReleaseSource, channel: Channel) -> bool {
+        self.api_url == source.api_url && self.repo == source.repo && self.channel == channel
+    }
+}
+
+/// Current time as a Unix timestamp
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn source(api_url: &str, download_url: &str, token_for_downloads: bool) -> ReleaseSource {
+        ReleaseSource {
+            api_url: api_url.to_string(),
+            download_url: download_url.to_string(),
+            repo: "o/r".to_string(),
+            token: Some("secret".to_string()),
+            token_for_downloads,
+        }
+    }
+    
+    fn release_with_api_asset(asset: &str) -> ReleaseInfo {
+        serde_json::from_value(serde_json::json!({
+            "tag": "v1.2.3",
+            "assets": [{
+                "name": asset,
+                "api_url": "https://api.github.com/repos/o/r/releases/assets/42",
+            }],
+        }))
+        .unwrap()
+    }
+    
+    #[test]
+    fn token_goes_only_to_configured_origins() {
+        let github = source(DEFAULT_API_URL, DEFAULT_DOWNLOAD_URL, false);
+        assert!(github.sends_token("https://api.github.com/repos/o/r/releases/latest"));
+        assert!(github.sends_token("https://api.github.com:443/repos/o/r/releases/assets/1"));
+        // Environment token: not for the download server, nor where it redirects
+        assert!(!github.sends_token("https://github.com/o/r/releases/download/v1/a.tar.gz"));
+        assert!(!github.sends_token("https://objects.githubusercontent.com/x"));
+        // Look-alike hosts, other schemes and ports
+        assert!(!github.sends_token("https://api.github.com.evil.example/repos"));
+        assert!(!github.sends_token("https://evil.example/?https://api.github.com"));
+        assert!(!github.sends_token("http://api.github.com/repos"));
+        assert!(!github.sends_token("https://api.github.com:8443/repos"));
+        assert!(!github.sends_token("file:///tmp/api.github.com"));
+        assert!(!github.sends_token("not a url"));
+        
+        // Token from dev.json, configured next to its mirror
+        let mirror = source("https://ghe.example/api/v3", "https://mirror.example/sdk", true);
+        assert!(mirror.sends_token("https://ghe.example/api/v3/repos/o/r/releases"));
+        assert!(mirror.sends_token("https://mirror.example/sdk/o/r/releases/download/v1/a.zip"));
+        assert!(!mirror.sends_token("https://api.github.com/repos/o/r/releases"));
+        assert!(!mirror.sends_token("https://cdn.mirror.example/a.zip"));
+        
+        let no_token = ReleaseSource { token: None, ..github };
+        assert!(!no_token.sends_token("https://api.github.com/repos/o/r/releases"));
+    }
+    
+    #[test]
+    fn asset_download_url_keeps_configured_mirror() {
+        let release = release_with_api_asset("mpf.tar.gz");
+        
+        let github = source(DEFAULT_API_URL, DEFAULT_DOWNLOAD_URL, false);
+        assert_eq!(
+            github.asset_download_url(Some(&release), "v1.2.3", "mpf.tar.gz"),
+            "https://api.github.com/repos/o/r/releases/assets/42"
+        );
+        assert_eq!(
+            github.asset_download_url(Some(&release), "v1.2.3", "other.zip"),
+            "https://github.com/o/r/releases/download/v1.2.3/other.zip"
+        );
+        
+        let mirror = source(DEFAULT_API_URL, "https://mirror.example", false);
+        assert_eq!(
+            mirror.asset_download_url(Some(&release), "v1.2.3", "mpf.tar.gz"),
+            "https://mirror.example/o/r/releases/download/v1.2.3/mpf.tar.gz"
+        );
+        
+        let no_token = ReleaseSource { token: None, ..github };
+        assert_eq!(
+            no_token.asset_download_url(Some(&release), "v1.2.3", "mpf.tar.gz"),
+            "https://github.com/o/r/releases/download/v1.2.3/mpf.tar.gz"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::env::consts::{ARCH, OS};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config;
+use crate::release::{self, ReleaseSource};
+
+/// Repository publishing mpf-dev's own releases
+pub const TOOL_REPO: &str = "dyzdyz010/mpf-dev";
+
+/// Release source for mpf-dev itself: GitHub and the tool's repository, whatever
+/// the SDK source is, so a mirror or SDK token is never used for it. Override with
+/// MPF_SELF_UPDATE_API_URL, MPF_SELF_UPDATE_DOWNLOAD_URL and MPF_SELF_UPDATE_REPO.
+pub fn tool_source() -> ReleaseSource {
+    let pick = |var: &str, default: &str| {
+        env::var(var)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| default.to_string())
+            .trim_end_matches('/')
+            .to_string()
+    };
+    ReleaseSource {
+        api_url: pick("MPF_SELF_UPDATE_API_URL", release::DEFAULT_API_URL),
+        download_url: pick("MPF_SELF_UPDATE_DOWNLOAD_URL", release::DEFAULT_DOWNLOAD_URL),
+        repo: pick("MPF_SELF_UPDATE_REPO", TOOL_REPO).trim_matches('/').to_string(),
+        // Only a GitHub token from the environment, and only for the API server
+        token: release::env_token().map(|t| t.trim().to_string()),
+        token_for_downloads: false,
+    }
+}
+
+/// Executable name inside the release archive
+fn binary_name() -> &'static str {
+    if cfg!(windows) {
+        "mpf-dev.exe"
+    } else {
+        "mpf-dev"
+    }
+}
+
+/// Release asset for this platform, e.g. `mpf-dev-linux-x86_64.tar.gz`
+pub fn asset_name() -> String {
+    let ext = if cfg!(windows) { "zip" } else { "tar.gz" };
+    format!("mpf-dev-{}-{}.{}", OS, ARCH, ext)
+}
+
+/// Copy of the previously installed executable, kept for `--rollback`
+pub fn backup_path() -> Result<PathBuf> {
+    Ok(config::sdk_root()?.join("self-update").join(binary_name()))
+}
+
+fn backup_version_path() -> Result<PathBuf> {
+    Ok(config::sdk_root()?.join("self-update").join("version.txt"))
+}
+
+/// Version of the backed-up executable
+pub fn backup_version() -> Option<String> {
+    fs::read_to_string(backup_version_path().ok()?)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Keep a copy of `exe` (running as `version`) for `--rollback`
+pub fn save_backup(exe: &Path, version: &str) -> Result<()> {
+    let backup = backup_path()?;
+    if let Some(parent) = backup.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(exe, &backup)
+        .with_context(|| format!("Failed to back up {}", exe.display()))?;
+    fs::write(backup_version_path()?, version)?;
+    Ok(())
+}
+
+/// Write the mpf-dev executable contained in a release archive to `dest`
+pub fn extract_binary(archive: &Path, is_tarball: bool, dest: &Path) -> Result<()> {
+    let file = File::open(archive)
+        .with_context(|| format!("Failed to open {}", archive.display()))?;
+    let is_binary = |path: &Path| path.file_name().is_some_and(|n| n == binary_name());
+    let mut found = false;
+    
+    if is_tarball {
+        let gz = flate2::read::GzDecoder::new(BufReader::new(file));
+        let mut tar = tar::Archive::new(gz);
+        for entry in tar.entries().context("Corrupted tar archive")? {
+            let mut entry = entry.context("Corrupted tar entry")?;
+            if entry.header().entry_type().is_file() && is_binary(&entry.path()?) {
+                io::copy(&mut entry, &mut File::create(dest)?)?;
+                found = true;
+                break;
+            }
+        }
+    } else {
+        let mut zip = zip::ZipArchive::new(BufReader::new(file)).context("Corrupted zip archive")?;
+        for i in 0..zip.len() {
+            let mut entry = zip.by_index(i).context("Corrupted zip entry")?;
+            if entry.is_file() && is_binary(Path::new(entry.name())) {
+                io::copy(&mut entry, &mut File::create(dest)?)?;
+                found = true;
+                break;
+            }
+        }
+    }
+    
+    if !found {
+        bail!("{} does not contain {}", archive.display(), binary_name());
+    }
+    make_executable(dest)
+}
+
+/// Run `<binary> --version` to make sure it starts on this machine, returning its output
+pub fn check_binary(binary: &Path) -> Result<String> {
+    let output = Command::new(binary)
+        .arg("--version")
+        .output()
+        .with_context(|| format!("Failed to run {}", binary.display()))?;
+    if !output.status.success() {
+        bail!("{} --version failed ({})", binary.display(), output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Copy `binary` next to `exe`, so the final rename stays on one filesystem
+pub fn stage(exe: &Path, binary: &Path) -> Result<PathBuf> {
+    let staged = exe.with_file_name(format!(".{}.new", binary_name()));
+    fs::copy(binary, &staged).with_context(|| {
+        format!(
+            "Failed to write {} (is the directory writable?)",
+            staged.display()
+        )
+    })?;
+    make_executable(&staged)?;
+    Ok(staged)
+}
+
+/// Atomically put a staged executable in place of `exe`.
+/// Windows cannot overwrite a running executable, but it can rename it out of the way.
+pub fn replace(exe: &Path, staged: &Path) -> Result<()> {
+    #[cfg(windows)]
+    {
+        let old = exe.with_file_name("mpf-dev.old.exe");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)
+            .with_context(|| format!("Failed to move {} aside", exe.display()))?;
+        if let Err(e) = fs::rename(staged, exe) {
+            let _ = fs::rename(&old, exe);
+            return Err(e).with_context(|| format!("Failed to replace {}", exe.display()));
+        }
+        Ok(())
+    }
+    #[cfg(not(windows))]
+    {
+        fs::rename(staged, exe).with_context(|| format!("Failed to replace {}", exe.display()))
+    }
+}
+
+fn make_executable(path: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use minisign_verify::{PublicKey, Signature};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::config::DevConfig;
+
+/// SDK signing key compiled into release builds (base64, the second line of minisign.pub)
+const BUILTIN_PUBLIC_KEY: Option<&str> = option_env!("MPF_SDK_PUBLIC_KEY");
+
+/// Detached minisign signature published next to an asset
+pub fn signature_name(asset: &str) -> String {
+    format!("{}.minisig", asset)
+}
+
+/// Keys trusted for SDK signatures and whether a valid signature is mandatory
+pub struct SignaturePolicy {
+    keys: Vec<PublicKey>,
+    required: bool,
+}
+
+impl SignaturePolicy {
+    /// The built-in key plus `release.public_key` and `release.require_signatures` from dev.json
+    pub fn load() -> Result<Self> {
+        let release = DevConfig::load()?.release.unwrap_or_default();
+        
+        let mut keys = Vec::new();
+        if let Some(key) = BUILTIN_PUBLIC_KEY.filter(|k| !k.trim().is_empty()) {
+            keys.push(parse_key(key).context("Invalid built-in SDK public key")?);
+        }
+        if let Some(key) = release.public_key.as_deref().filter(|k| !k.trim().is_empty()) {
+            keys.push(parse_key(key).context("Invalid release.public_key in dev.json")?);
+        }
+        
+        let required = release.require_signatures.unwrap_or(false);
+        if required && keys.is_empty() {
+            bail!("release.require_signatures is enabled in dev.json but no public key is configured (set release.public_key)");
+        }
+        Ok(Self { keys, required })
+    }
+    
+    /// Whether there is a key to check signatures with (otherwise they are not fetched)
+    pub fn is_active(&self) -> bool {
+        !self.keys.is_empty()
+    }
+    
+    pub fn required(&self) -> bool {
+        self.required
+    }
+    
+    /// Refuse `--skip-verify` when signatures are mandatory
+    pub fn check_skip(&self) -> Result<()> {
+        if self.required {
+            bail!("--skip-verify cannot be used while release.require_signatures is enabled");
+        }
+        Ok(())
+    }
+    
+    /// Check `path` against its detached signature, if one was published
+    pub fn verify(&self, path: &Path, name: &str, signature: Option<&str>) -> Result<()> {
+        let Some(signature) = signature else {
+            if self.required {
+                bail!(
+                    "No signature ({}) found for {}; refusing to install because release.require_signatures is enabled",
+                    signature_name(name),
+                    name
+                );
+            }
+            if self.is_active() {
+                println!(
+                    "{} No signature published for {}; skipping signature verification",
+                    "Note:".yellow(),
+                    name
+                );
+            }
+            return Ok(());
+        };
+        if !self.is_active() {
+            return Ok(());
+        }
+        
+        let signature = Signature::decode(signature)
+            .with_context(|| format!("Invalid signature file {}", signature_name(name)))?;
+        
+        // Only the key the signature names can verify it
+        let mut verifier = None;
+        let mut last_error = None;
+        for key in &self.keys {
+            match key.verify_stream(&signature) {
+                Ok(v) => {
+                    verifier = Some(v);
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        let Some(mut verifier) = verifier else {
+            bail!(
+                "Cannot verify the signature of {}: {}",
+                name,
+                last_error.map(|e| e.to_string()).unwrap_or_default()
+            );
+        };
+        
+        let mut file = File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            verifier.update(&buf[..n]);
+        }
+        if verifier.finalize().is_err() {
+            bail!(
+                "Signature verification failed for {}\nThe archive may have been tampered with; refusing to install.",
+                name
+            );
+        }
+        
+        println!("{} Signature verified (minisign)", "✓".green());
+        Ok(())
+    }
+}
+
+/// Accept either the bare base64 key or the whole minisign.pub file content
+fn parse_key(text: &str) -> Result<PublicKey> {
+    let text = text.trim();
+    let key = if text.contains('\n') {
+        PublicKey::decode(text)
+    } else {
+        PublicKey::from_base64(text)
+    };
+    key.map_err(|e| anyhow::anyhow!("{}", e))
+}
@@ -0,0 +1,360 @@
+use anyhow::{bail, Result};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// Release channel, mapped to prerelease tags:
+/// stable = no prerelease, beta = `-beta.N`/`-rc.N`, nightly = any prerelease
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    #[default]
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl Channel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+    
+    /// Whether a release with this version belongs to the channel
+    /// (stable releases are part of every channel)
+    pub fn accepts(&self, version: &Version) -> bool {
+        if version.pre.is_empty() {
+            return true;
+        }
+        match self {
+            Channel::Stable => false,
+            Channel::Beta => {
+                let pre = version.pre.as_str();
+                pre.starts_with("beta") || pre.starts_with("rc")
+            }
+            Channel::Nightly => true,
+        }
+    }
+}
+
+/// What the user asked for in `setup --version` / `use`
+#[derive(Debug, Clone)]
+pub enum VersionSpec {
+    /// Newest release in the channel
+    Latest,
+    /// Newest release with the same major.minor as the current version
+    LatestPatch,
+    /// One exact tag, used as-is (normalized with a `v` prefix), e.g. `1.2.3` or `1.2`
+    Exact(String),
+    /// Semver range with an explicit operator or wildcard, such as `^1.2`, `~1.2.3` or `1.2.*`
+    Range(VersionReq),
+}
+
+impl VersionSpec {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        match spec {
+            "" => bail!("Empty version"),
+            "latest" => return Ok(Self::Latest),
+            "latest-patch" => return Ok(Self::LatestPatch),
+            _ => {}
+        }
+        
+        // Ranges are always explicit ("^1.2", ">=1.2", "1.2.*"); anything else names
+        // one tag, so "1.2" means the tag v1.2 and never "any 1.x from 1.2 up"
+        let is_range = spec.starts_with(['^', '~', '=', '<', '>', '*'])
+            || spec.contains(',')
+            || spec.split('.').any(|part| matches!(part.trim(), "*" | "x" | "X"));
+        if !is_range {
+            let tag = normalize(spec);
+            validate(&tag)?;
+            return Ok(Self::Exact(tag));
+        }
+        
+        // Allow a `v` after the operator, e.g. "^v1.2"
+        let cleaned: String = spec
+            .split(',')
+            .map(|part| {
+                let part = part.trim();
+                let op_len = part.len() - part.trim_start_matches(['^', '~', '=', '<', '>']).len();
+                let (op, rest) = part.split_at(op_len);
+                format!("{}{}", op, rest.trim_start().trim_start_matches('v'))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        
+        match VersionReq::parse(&cleaned) {
+            Ok(req) => Ok(Self::Range(req)),
+            Err(e) => bail!("Invalid version range '{}': {}", spec, e),
+        }
+    }
+    
+    /// Pick the highest tag from `candidates` matching this spec.
+    /// `current` is required for `latest-patch`.
+    pub fn resolve<'a>(
+        &self,
+        candidates: impl IntoIterator<Item = &'a String>,
+        channel: Channel,
+        current: Option<&str>,
+    ) -> Result<Option<String>> {
+        let current_version = current.and_then(parse_version);
+        if matches!(self, Self::LatestPatch) && current_version.is_none() {
+            bail!("latest-patch needs a current SDK version; run `mpf-dev use <version>` first");
+        }
+        
+        let best = candidates
+            .into_iter()
+            .filter_map(|tag| parse_version(tag).map(|v| (tag, v)))
+            .filter(|(tag, v)| match self {
+                Self::Latest => channel.accepts(v),
+                Self::LatestPatch => {
+                    let cur = current_version.as_ref().expect("checked above");
+                    channel.accepts(v) && v.major == cur.major && v.minor == cur.minor
+                }
+                Self::Exact(exact) => *tag == exact,
+                Self::Range(req) => channel.accepts(v) && range_matches(req, v),
+            })
+            .max_by(|(_, a), (_, b)| a.cmp(b))
+            .map(|(tag, _)| tag.clone());
+        
+        Ok(best)
+    }
+}
+
+/// Semver range check that lets channel-approved prereleases match by their release number
+fn range_matches(req: &VersionReq, version: &Version) -> bool {
+    if req.matches(version) {
+        return true;
+    }
+    if version.pre.is_empty() {
+        return false;
+    }
+    let mut release = version.clone();
+    release.pre = semver::Prerelease::EMPTY;
+    req.matches(&release)
+}
+
+/// Normalize a version tag to the "vX.Y.Z" directory name form
+pub fn normalize(version: &str) -> String {
+    if version.starts_with('v') {
+        version.to_string()
+    } else {
+        format!("v{}", version)
+    }
+}
+
+/// Make sure a version tag is usable as a directory name: one plain path component,
+/// so a version from a pin file, the environment or the command line can never
+/// point outside the SDK root
+pub fn validate(tag: &str) -> Result<()> {
+    let invalid = tag.is_empty()
+        || tag == "."
+        || tag == ".."
+        || tag.trim() != tag
+        || tag.contains(['/', '\\', ':'])
+        || tag.chars().any(|c| c.is_control());
+    if invalid {
+        bail!("Invalid SDK version '{}': expected a single version tag such as v1.2.3", tag);
+    }
+    Ok(())
+}
+
+/// Parse a tag such as "v1.2.3", "1.2" or "v1.3.0-beta.1" as a semantic version
+pub fn parse_version(tag: &str) -> Option<Version> {
+    let raw = tag.trim().trim_start_matches('v');
+    if let Ok(v) = Version::parse(raw) {
+        return Some(v);
+    }
+    // Pad short forms ("1.2" -> "1.2.0"), keeping any prerelease/build suffix
+    let (core, suffix) = match raw.find(['-', '+']) {
+        Some(i) => raw.split_at(i),
+        None => (raw, ""),
+    };
+    let mut parts: Vec<&str> = core.split('.').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    Version::parse(&format!("{}{}", parts.join("."), suffix)).ok()
+}
+
+/// Order version tags semantically; tags that are not semver sort first, by name
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (parse_version(a), parse_version(b)) {
+        (Some(va), Some(vb)) => va.cmp(&vb).then_with(|| a.cmp(b)),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn exact(spec: &str) -> Option<String> {
+        match VersionSpec::parse(spec).unwrap() {
+            VersionSpec::Exact(tag) => Some(tag),
+            _ => None,
+        }
+    }
+    
+    fn range(spec: &str) -> VersionReq {
+        match VersionSpec::parse(spec).unwrap() {
+            VersionSpec::Range(req) => req,
+            other => panic!("{} parsed as {:?}", spec, other),
+        }
+    }
+    
+    fn installed() -> Vec<String> {
+        ["v1.0.0", "v1.0.1", "v1.2.0", "v1.3.5", "v1.4.0-beta.1", "v2.0.0"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect()
+    }
+    
+    #[test]
+    fn keywords() {
+        assert!(matches!(VersionSpec::parse("latest").unwrap(), VersionSpec::Latest));
+        assert!(matches!(VersionSpec::parse(" latest-patch ").unwrap(), VersionSpec::LatestPatch));
+        assert!(VersionSpec::parse("").is_err());
+        assert!(VersionSpec::parse("  ").is_err());
+    }
+    
+    #[test]
+    fn versions_without_operator_are_exact_tags() {
+        assert_eq!(exact("1.2.3").as_deref(), Some("v1.2.3"));
+        assert_eq!(exact("v1.2.3").as_deref(), Some("v1.2.3"));
+        assert_eq!(exact("1.0").as_deref(), Some("v1.0"));
+        assert_eq!(exact("1").as_deref(), Some("v1"));
+        assert_eq!(exact("1.3.0-beta.1").as_deref(), Some("v1.3.0-beta.1"));
+        assert_eq!(exact("nightly-2024").as_deref(), Some("vnightly-2024"));
+    }
+    
+    #[test]
+    fn partial_versions_do_not_match_other_releases() {
+        let candidates = installed();
+        let resolve = |spec: &str| {
+            VersionSpec::parse(spec)
+                .unwrap()
+                .resolve(&candidates, Channel::Stable, None)
+                .unwrap()
+        };
+        assert_eq!(resolve("1.0"), None);
+        assert_eq!(resolve("1.2"), None);
+        assert_eq!(resolve("1.0.1").as_deref(), Some("v1.0.1"));
+    }
+    
+    #[test]
+    fn explicit_ranges() {
+        assert!(range("^1.2").matches(&Version::new(1, 3, 5)));
+        assert!(!range("^1.2").matches(&Version::new(2, 0, 0)));
+        assert!(range("~1.2.3").matches(&Version::new(1, 2, 9)));
+        assert!(!range("~1.2.3").matches(&Version::new(1, 3, 0)));
+        assert!(range("^v1.2").matches(&Version::new(1, 2, 0)));
+        assert!(range(">=1.1, <2").matches(&Version::new(1, 9, 0)));
+        assert!(range("1.2.*").matches(&Version::new(1, 2, 7)));
+        assert!(!range("1.2.*").matches(&Version::new(1, 3, 0)));
+        assert!(range("*").matches(&Version::new(0, 1, 0)));
+    }
+    
+    #[test]
+    fn invalid_ranges() {
+        assert!(VersionSpec::parse("^").is_err());
+        assert!(VersionSpec::parse(">=abc").is_err());
+        assert!(VersionSpec::parse("~1.2.3.4").is_err());
+    }
+    
+    #[test]
+    fn ranges_pick_the_highest_match_in_the_channel() {
+        let candidates = installed();
+        let resolve = |spec: &str, channel| {
+            VersionSpec::parse(spec)
+                .unwrap()
+                .resolve(&candidates, channel, None)
+                .unwrap()
+        };
+        assert_eq!(resolve("^1.2", Channel::Stable).as_deref(), Some("v1.3.5"));
+        assert_eq!(resolve("^1.2", Channel::Beta).as_deref(), Some("v1.4.0-beta.1"));
+        assert_eq!(resolve("~1.0", Channel::Stable).as_deref(), Some("v1.0.1"));
+        assert_eq!(resolve("latest", Channel::Stable).as_deref(), Some("v2.0.0"));
+        assert_eq!(resolve("^3", Channel::Stable), None);
+    }
+    
+    #[test]
+    fn latest_patch_needs_current_version() {
+        let candidates = installed();
+        let spec = VersionSpec::parse("latest-patch").unwrap();
+        assert!(spec.resolve(&candidates, Channel::Stable, None).is_err());
+        assert_eq!(
+            spec.resolve(&candidates, Channel::Stable, Some("v1.0.0")).unwrap().as_deref(),
+            Some("v1.0.1")
+        );
+    }
+    
+    #[test]
+    fn exact_tags_must_be_plain_names() {
+        for spec in ["1.2/../../x", "../v1.0.0", "/opt/v1.0.0", r"1.0\..\x", "C:1.0", "v1.0:x"] {
+            assert!(VersionSpec::parse(spec).is_err(), "{} accepted", spec);
+        }
+    }
+    
+    #[test]
+    fn parse_version_forms() {
+        assert_eq!(parse_version("v1.2.3"), Some(Version::new(1, 2, 3)));
+        assert_eq!(parse_version(" 1.2.3 "), Some(Version::new(1, 2, 3)));
+        assert_eq!(parse_version("1.2"), Some(Version::new(1, 2, 0)));
+        assert_eq!(parse_version("v2"), Some(Version::new(2, 0, 0)));
+        assert_eq!(
+            parse_version("v1.3.0-beta.1"),
+            Some(Version::parse("1.3.0-beta.1").unwrap())
+        );
+        assert_eq!(parse_version("1.3-rc.1"), Some(Version::parse("1.3.0-rc.1").unwrap()));
+        assert_eq!(parse_version("1.2.3.4"), None);
+        assert_eq!(parse_version("latest"), None);
+        assert_eq!(parse_version(""), None);
+        assert_eq!(parse_version("1..2"), None);
+    }
+    
+    #[test]
+    fn plain_tags_are_valid() {
+        for tag in ["v1.2.3", "1.2.3", "v1.3.0-beta.1", "v1.0.0+build.5", "nightly_2024", "v..1"] {
+            assert!(validate(tag).is_ok(), "{} rejected", tag);
+        }
+    }
+    
+    #[test]
+    fn path_like_tags_are_rejected() {
+        let rejected = [
+            "",
+            ".",
+            "..",
+            " v1.0.0",
+            "v1.0.0\n",
+            "1.0.0/../../proj/evil",
+            "../evil",
+            "/opt/evil",
+            "v1/..",
+            r"..\evil",
+            r"\\server\share",
+            r"C:\evil",
+            "C:evil",
+            "v1\0",
+        ];
+        for tag in rejected {
+            assert!(validate(tag).is_err(), "{:?} accepted", tag);
+        }
+    }
+    
+    #[test]
+    fn compare_orders_semantically() {
+        assert_eq!(compare_versions("v1.10.0", "v1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("v1.2.0-beta.1", "v1.2.0"), Ordering::Less);
+        assert_eq!(compare_versions("custom", "v0.0.1"), Ordering::Less);
+    }
+}